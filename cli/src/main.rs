@@ -1,8 +1,9 @@
 #![deny(clippy::dbg_macro)]
 
 use std::{
-    env, fs,
-    io::{Read, Write},
+    any::Any,
+    env, fmt, fs,
+    io::{self, Read, Write},
     panic,
     path::{Path, PathBuf},
     process::{self, exit, Stdio},
@@ -10,17 +11,31 @@ use std::{
 };
 
 use anyhow::Context;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use console::Style;
-use glob::{glob, GlobError, Pattern};
-use leptosfmt_formatter::{format_file_source, FormatterSettings};
+use glob::{glob, Pattern};
+use leptosfmt_formatter::{
+    format_file_source_with_outcome, FormatError, FormatOutcome, FormatterSettings,
+};
 use rayon::{iter::ParallelIterator, prelude::IntoParallelIterator};
 use similar::{ChangeTag, TextDiff};
 
+mod stats;
+
 /// A formatter for Leptos RSX sytnax
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// A space separated list of file, directory or glob
     #[arg(required_unless_present = "stdin")]
     input_patterns: Option<Vec<String>>,
@@ -76,6 +91,137 @@ struct Args {
     /// Check if the file is correctly formatted. Exit with code 1 if not.
     #[arg(long, default_value = "false")]
     check: bool,
+
+    /// Print a table with the number of files per outcome
+    #[arg(long, default_value = "false")]
+    summary: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List every view macro with its size and whether it needs formatting
+    Stats(stats::StatsArgs),
+}
+
+// Exit codes, when multiple apply the most severe (highest) one is used
+const EXIT_CHECK_FAILED: i32 = 1;
+const EXIT_PARSE_ERROR: i32 = 2;
+const EXIT_IO_ERROR: i32 = 3;
+const EXIT_INTERNAL_PANIC: i32 = 4;
+/// Invalid arguments, config file or glob pattern, no files are formatted
+const EXIT_USAGE_ERROR: i32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileStatus {
+    Formatted,
+    Unchanged,
+    /// Excluded with `// leptosfmt::skip-file`, or without view macros to format
+    Skipped,
+    ParseError,
+    IoError,
+    InternalPanic,
+}
+
+impl FileStatus {
+    const ALL: [FileStatus; 6] = [
+        FileStatus::Formatted,
+        FileStatus::Unchanged,
+        FileStatus::Skipped,
+        FileStatus::ParseError,
+        FileStatus::IoError,
+        FileStatus::InternalPanic,
+    ];
+
+    fn label(self, check: bool) -> &'static str {
+        match self {
+            FileStatus::Formatted if check => "needs formatting",
+            FileStatus::Formatted => "formatted",
+            FileStatus::Unchanged => "unchanged",
+            FileStatus::Skipped => "skipped",
+            FileStatus::ParseError => "parse error",
+            FileStatus::IoError => "io error",
+            FileStatus::InternalPanic => "internal panic",
+        }
+    }
+
+    fn exit_code(self, check: bool) -> i32 {
+        match self {
+            FileStatus::Formatted if check => EXIT_CHECK_FAILED,
+            FileStatus::Formatted | FileStatus::Unchanged | FileStatus::Skipped => 0,
+            FileStatus::ParseError => EXIT_PARSE_ERROR,
+            FileStatus::IoError => EXIT_IO_ERROR,
+            FileStatus::InternalPanic => EXIT_INTERNAL_PANIC,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum FormatFailure {
+    /// The file could not be read or written
+    Io(io::Error),
+    /// The file could not be parsed
+    Parse(FormatError),
+    /// leptosfmt panicked while formatting the file
+    Panic(String),
+}
+
+impl FormatFailure {
+    fn status(&self) -> FileStatus {
+        match self {
+            FormatFailure::Io(_) => FileStatus::IoError,
+            FormatFailure::Parse(_) => FileStatus::ParseError,
+            FormatFailure::Panic(_) => FileStatus::InternalPanic,
+        }
+    }
+}
+
+impl fmt::Display for FormatFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatFailure::Io(err) => write!(f, "could not read or write file: {err}"),
+            FormatFailure::Parse(err) => write!(f, "{err}"),
+            FormatFailure::Panic(msg) => write!(f, "leptosfmt panicked: {msg}"),
+        }
+    }
+}
+
+impl From<io::Error> for FormatFailure {
+    fn from(err: io::Error) -> Self {
+        FormatFailure::Io(err)
+    }
+}
+
+impl From<FormatError> for FormatFailure {
+    fn from(err: FormatError) -> Self {
+        match err {
+            FormatError::IoError(err) => FormatFailure::Io(err),
+            err => FormatFailure::Parse(err),
+        }
+    }
+}
+
+/// The exit code of a run, the most severe (highest) exit code of all files
+fn exit_code(statuses: &[FileStatus], check: bool) -> i32 {
+    statuses
+        .iter()
+        .map(|status| status.exit_code(check))
+        .max()
+        .unwrap_or_default()
+}
+
+/// Reports an invalid argument, config file or glob pattern and exits
+fn usage_error(err: anyhow::Error) -> ! {
+    eprintln!("❌ {err:#}");
+    exit(EXIT_USAGE_ERROR)
+}
+
+fn print_summary(statuses: &[FileStatus], check: bool) {
+    println!("{:<20}{:>8}", "Outcome", "Files");
+    for status in FileStatus::ALL {
+        let count = statuses.iter().filter(|s| **s == status).count();
+        println!("{:<20}{:>8}", status.label(check), count);
+    }
+    println!("{:<20}{:>8}", "total", statuses.len());
 }
 
 fn check_if_diff(path: Option<&PathBuf>, original: &str, formatted: &str, quiet: bool) -> bool {
@@ -105,8 +251,22 @@ fn check_if_diff(path: Option<&PathBuf>, original: &str, formatted: &str, quiet:
 }
 
 fn main() {
-    let args = Args::parse();
-    let settings = create_settings(&args).unwrap();
+    let args = Args::try_parse().unwrap_or_else(|err| {
+        // `--help` and `--version` are reported as errors as well
+        let code = if err.use_stderr() {
+            EXIT_USAGE_ERROR
+        } else {
+            0
+        };
+        let _ = err.print();
+        exit(code)
+    });
+    if let Some(Command::Stats(stats_args)) = args.command {
+        stats::run(stats_args);
+        return;
+    }
+
+    let settings = create_settings(&args).unwrap_or_else(|err| usage_error(err));
     let quiet = args.quiet;
 
     // Print settings
@@ -119,20 +279,21 @@ fn main() {
             Ok(FormatOutput {
                 original,
                 mut formatted,
+                ..
             }) => {
                 if args.rustfmt {
                     formatted = run_rustfmt(&formatted, &args.rustfmt_args).unwrap_or(formatted);
                 }
 
                 if args.check && check_if_diff(None, &original, &formatted, true) {
-                    exit(1)
+                    exit(EXIT_CHECK_FAILED)
                 } else {
                     print!("{formatted}")
                 }
             }
             Err(err) => {
                 eprintln!("{err}");
-                exit(1)
+                exit(err.status().exit_code(args.check))
             }
         }
         return;
@@ -140,8 +301,7 @@ fn main() {
 
     if args.rustfmt {
         // TODO: didn't dive into this yet, but `requires` clap attribute doesn't seem to work
-        eprintln!("❌ --rustfmt requires --stdin");
-        exit(1);
+        usage_error(anyhow::anyhow!("--rustfmt requires --stdin"));
     }
 
     let print_err = |path: &Path, err| {
@@ -149,9 +309,12 @@ fn main() {
         eprintln!("\t\t{}", err);
     };
 
-    let input_patterns = args.input_patterns.unwrap();
+    let Some(input_patterns) = args.input_patterns else {
+        usage_error(anyhow::anyhow!("no input files given"));
+    };
     let exclude_patterns = args.exclude_patterns.unwrap_or_default();
-    let file_paths: Vec<_> = get_file_paths(input_patterns, exclude_patterns).unwrap();
+    let file_paths =
+        get_file_paths(input_patterns, exclude_patterns).unwrap_or_else(|err| usage_error(err));
 
     let total_files = file_paths.len();
     let start_formatting = Instant::now();
@@ -161,20 +324,34 @@ fn main() {
         .map(|path| (path.clone(), format_file(&path, &settings, !args.check)))
         .collect::<Vec<_>>();
 
-    let mut check_failed = false;
+    let mut statuses = Vec::with_capacity(total_files);
     for (path, result) in format_results {
-        match result {
+        let status = match result {
             Ok(r) => {
-                if args.check && check_if_diff(Some(&path), &r.original, &r.formatted, quiet) {
-                    check_failed = true;
-                }
+                let changed = if args.check {
+                    check_if_diff(Some(&path), &r.original, &r.formatted, quiet)
+                } else {
+                    r.original != r.formatted
+                };
 
                 if !quiet {
                     println!("✅ {}", path.display())
                 }
+
+                if r.skipped {
+                    FileStatus::Skipped
+                } else if changed {
+                    FileStatus::Formatted
+                } else {
+                    FileStatus::Unchanged
+                }
             }
-            Err(err) => print_err(&path, err.to_string()),
-        }
+            Err(err) => {
+                print_err(&path, err.to_string());
+                err.status()
+            }
+        };
+        statuses.push(status);
     }
 
     let end_formatting = Instant::now();
@@ -187,9 +364,18 @@ fn main() {
         )
     }
 
-    if check_failed {
+    if args.summary {
+        print_summary(&statuses, args.check);
+    }
+
+    let exit_code = exit_code(&statuses, args.check);
+
+    if exit_code == EXIT_CHECK_FAILED {
         eprintln!("❌ Some files are not correctly formatted, see the diff above");
-        exit(1);
+    }
+
+    if exit_code != 0 {
+        exit(exit_code);
     }
 }
 
@@ -206,66 +392,104 @@ fn as_glob_pattern(pattern: String) -> String {
 fn get_file_paths(
     input_patterns: Vec<String>,
     exclude_patterns: Vec<String>,
-) -> Result<Vec<PathBuf>, GlobError> {
+) -> anyhow::Result<Vec<PathBuf>> {
     let exclude_patterns = exclude_patterns
         .into_iter()
         .map(as_glob_pattern)
-        .map(|p| Pattern::new(&p))
-        .collect::<Result<Vec<_>, _>>()
-        .expect("failed to parse exclude glob pattern");
+        .map(|p| Pattern::new(&p).with_context(|| format!("invalid exclude pattern: {p}")))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut file_paths = Vec::new();
+    for glob_pattern in input_patterns.into_iter().map(as_glob_pattern) {
+        let paths = glob(&glob_pattern)
+            .with_context(|| format!("invalid input pattern: {glob_pattern}"))?;
+
+        // Paths that cannot be read while matching the pattern are left out
+        for path in paths.flatten() {
+            if !exclude_patterns
+                .iter()
+                .any(|pattern| pattern.matches_path(&path))
+            {
+                file_paths.push(path);
+            }
+        }
+    }
 
-    input_patterns
-        .into_iter()
-        .map(as_glob_pattern)
-        .flat_map(|glob_pattern| {
-            glob(&glob_pattern)
-                .expect("failed to read glob pattern")
-                .filter(|is_file| {
-                    is_file.as_ref().is_ok_and(|file| {
-                        !exclude_patterns
-                            .iter()
-                            .any(|pattern| pattern.matches_path(file))
-                    })
-                })
-        })
-        .collect()
+    Ok(file_paths)
 }
 
 struct FormatOutput {
     original: String,
     formatted: String,
+    /// The file was left untouched by the formatter
+    skipped: bool,
 }
 
-fn format_stdin(settings: FormatterSettings) -> anyhow::Result<FormatOutput> {
-    let mut stdin = String::new();
-    let _ = std::io::stdin().read_to_string(&mut stdin);
+impl FormatOutput {
+    fn new(original: String, outcome: FormatOutcome) -> Self {
+        match outcome {
+            FormatOutcome::Formatted(formatted) => FormatOutput {
+                original,
+                formatted,
+                skipped: false,
+            },
+            FormatOutcome::Skipped => FormatOutput {
+                formatted: original.clone(),
+                original,
+                skipped: true,
+            },
+        }
+    }
+}
 
-    let formatted = panic::catch_unwind(|| format_file_source(&stdin, &settings))
-        .map_err(|e| anyhow::anyhow!(e.downcast::<String>().unwrap()))??;
+fn format_stdin(settings: FormatterSettings) -> Result<FormatOutput, FormatFailure> {
+    let mut stdin = String::new();
+    std::io::stdin().read_to_string(&mut stdin)?;
 
-    Ok(FormatOutput {
-        original: stdin,
-        formatted,
-    })
+    let outcome = format_source(&stdin, &settings)?;
+    Ok(FormatOutput::new(stdin, outcome))
 }
 
 fn format_file(
     file: &PathBuf,
     settings: &FormatterSettings,
     write_result: bool,
-) -> anyhow::Result<FormatOutput> {
+) -> Result<FormatOutput, FormatFailure> {
     let file_source = std::fs::read_to_string(file)?;
-    let formatted = panic::catch_unwind(|| format_file_source(&file_source, settings))
-        .map_err(|e| anyhow::anyhow!(e.downcast::<String>().unwrap()))??;
+    let outcome = format_source(&file_source, settings)?;
+    let output = FormatOutput::new(file_source, outcome);
 
-    if write_result && file_source != formatted {
-        fs::write(file, &formatted)?;
+    if write_result && output.original != output.formatted {
+        fs::write(file, &output.formatted)?;
     }
 
-    Ok(FormatOutput {
-        original: file_source,
-        formatted,
-    })
+    Ok(output)
+}
+
+fn format_source(
+    source: &str,
+    settings: &FormatterSettings,
+) -> Result<FormatOutcome, FormatFailure> {
+    format_source_with(|| format_file_source_with_outcome(source, settings))
+}
+
+/// Runs the formatter, reporting a panic as a failure of the file instead of aborting
+fn format_source_with<T>(
+    format: impl FnOnce() -> Result<T, FormatError> + panic::UnwindSafe,
+) -> Result<T, FormatFailure> {
+    panic::catch_unwind(format)
+        .map_err(|payload| FormatFailure::Panic(panic_message(payload)))?
+        .map_err(FormatFailure::from)
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(msg) => *msg,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map(|msg| msg.to_string())
+            .unwrap_or_else(|| "unknown panic".to_string()),
+    }
 }
 
 fn find_config_file() -> anyhow::Result<Option<PathBuf>> {
@@ -276,17 +500,7 @@ fn find_config_file() -> anyhow::Result<Option<PathBuf>> {
 }
 
 fn create_settings(args: &Args) -> anyhow::Result<FormatterSettings> {
-    let mut settings = args
-        .config_file
-        .as_ref()
-        .map(load_config)
-        .or_else(|| {
-            find_config_file()
-                .and_then(|v| v.as_ref().map(load_config).transpose())
-                .transpose()
-        })
-        .transpose()?
-        .unwrap_or_default();
+    let mut settings = load_settings(args.config_file.as_ref())?;
 
     if let Some(max_width) = args.max_width {
         settings.max_width = max_width;
@@ -315,6 +529,19 @@ fn create_settings(args: &Args) -> anyhow::Result<FormatterSettings> {
     Ok(settings)
 }
 
+/// Loads the given config file, or else the nearest `leptosfmt.toml`, or else the default settings
+fn load_settings(config_file: Option<&PathBuf>) -> anyhow::Result<FormatterSettings> {
+    Ok(config_file
+        .map(load_config)
+        .or_else(|| {
+            find_config_file()
+                .and_then(|v| v.as_ref().map(load_config).transpose())
+                .transpose()
+        })
+        .transpose()?
+        .unwrap_or_default())
+}
+
 fn load_config(path: &PathBuf) -> anyhow::Result<FormatterSettings> {
     fs::read_to_string(path)
         .context("could not read config file")
@@ -345,3 +572,72 @@ fn run_rustfmt(source: &str, args: &[String]) -> Option<String> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_code_of_no_files() {
+        assert_eq!(exit_code(&[], true), 0);
+    }
+
+    #[test]
+    fn exit_code_formatted() {
+        let statuses = [FileStatus::Unchanged, FileStatus::Formatted];
+        assert_eq!(exit_code(&statuses, false), 0);
+        assert_eq!(exit_code(&statuses, true), EXIT_CHECK_FAILED);
+    }
+
+    #[test]
+    fn exit_code_precedence() {
+        use FileStatus::*;
+
+        let cases = [
+            (vec![Formatted, ParseError], EXIT_PARSE_ERROR),
+            (vec![ParseError, IoError, Formatted], EXIT_IO_ERROR),
+            (
+                vec![IoError, InternalPanic, ParseError],
+                EXIT_INTERNAL_PANIC,
+            ),
+        ];
+
+        for (mut statuses, expected) in cases {
+            assert_eq!(exit_code(&statuses, true), expected, "{statuses:?}");
+            statuses.reverse();
+            assert_eq!(exit_code(&statuses, true), expected, "{statuses:?}");
+        }
+    }
+
+    #[test]
+    fn skipped_files() {
+        let settings = FormatterSettings::default();
+        let output = FormatOutput::new(
+            "// leptosfmt::skip-file\n".to_string(),
+            format_source("// leptosfmt::skip-file\n", &settings).unwrap(),
+        );
+        assert!(output.skipped);
+        assert_eq!(output.original, output.formatted);
+
+        let statuses = [FileStatus::Skipped, FileStatus::Unchanged];
+        assert_eq!(exit_code(&statuses, true), 0);
+        assert_eq!(FileStatus::Skipped.label(true), "skipped");
+        assert!(FileStatus::ALL.contains(&FileStatus::Skipped));
+    }
+
+    #[test]
+    fn io_errors_are_not_parse_errors() {
+        let io = FormatFailure::from(FormatError::IoError(io::Error::other("denied")));
+        assert_eq!(io.status(), FileStatus::IoError);
+        assert_eq!(io.status().exit_code(false), EXIT_IO_ERROR);
+    }
+
+    #[test]
+    fn invalid_glob_pattern() {
+        let err = get_file_paths(vec!["src/[".to_string()], Vec::new()).unwrap_err();
+        assert!(err.to_string().contains("invalid input pattern"));
+
+        let err = get_file_paths(Vec::new(), vec!["src/[".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("invalid exclude pattern"));
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+    time::Instant,
+};
+
+use leptosfmt_formatter::{view_macro_stats, FormatterSettings, ViewMacroStats};
+use rayon::{iter::ParallelIterator, prelude::IntoParallelIterator};
+use serde::{Serialize, Serializer};
+
+use crate::{
+    exit_code, format_source_with, get_file_paths, load_settings, usage_error, FormatFailure,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct StatsArgs {
+    /// A space separated list of file, directory or glob
+    #[arg(required = true)]
+    input_patterns: Vec<String>,
+
+    /// A space separated list of file or directory
+    #[arg(short = 'x', long = "excludes")]
+    exclude_patterns: Option<Vec<String>>,
+
+    /// Configuration file
+    #[arg(short, long)]
+    config_file: Option<PathBuf>,
+
+    /// Number of slowest files to list
+    #[arg(long, default_value = "10")]
+    slowest: usize,
+
+    /// Print the statistics as JSON
+    #[arg(long, default_value = "false")]
+    json: bool,
+}
+
+#[derive(Serialize)]
+struct StatsReport {
+    files: usize,
+    files_needing_formatting: usize,
+    macros: usize,
+    macros_needing_formatting: usize,
+    slowest_files: Vec<FileTiming>,
+    file_stats: Vec<FileStats>,
+}
+
+#[derive(Serialize)]
+struct FileStats {
+    path: PathBuf,
+    duration_ms: f64,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_error"
+    )]
+    error: Option<FormatFailure>,
+    macros: Vec<ViewMacroStats>,
+}
+
+impl FileStats {
+    fn needs_formatting(&self) -> bool {
+        self.macros.iter().any(|m| m.needs_formatting)
+    }
+}
+
+#[derive(Serialize)]
+struct FileTiming {
+    path: PathBuf,
+    duration_ms: f64,
+}
+
+pub fn run(args: StatsArgs) {
+    let settings = load_settings(args.config_file.as_ref()).unwrap_or_else(|err| usage_error(err));
+    let exclude_patterns = args.exclude_patterns.unwrap_or_default();
+    let file_paths = get_file_paths(args.input_patterns, exclude_patterns)
+        .unwrap_or_else(|err| usage_error(err));
+
+    let mut file_stats = file_paths
+        .into_par_iter()
+        .map(|path| collect_file_stats(path, &settings))
+        .collect::<Vec<_>>();
+    file_stats.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut slowest_files: Vec<_> = file_stats
+        .iter()
+        .map(|file| FileTiming {
+            path: file.path.clone(),
+            duration_ms: file.duration_ms,
+        })
+        .collect();
+    slowest_files.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+    slowest_files.truncate(args.slowest);
+
+    let report = StatsReport {
+        files: file_stats.len(),
+        files_needing_formatting: file_stats.iter().filter(|f| f.needs_formatting()).count(),
+        macros: file_stats.iter().map(|f| f.macros.len()).sum(),
+        macros_needing_formatting: file_stats
+            .iter()
+            .flat_map(|f| &f.macros)
+            .filter(|m| m.needs_formatting)
+            .count(),
+        slowest_files,
+        file_stats,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        print_report(&report);
+    }
+
+    let statuses: Vec<_> = report
+        .file_stats
+        .iter()
+        .filter_map(|file| file.error.as_ref().map(FormatFailure::status))
+        .collect();
+    let exit_code = exit_code(&statuses, false);
+    if exit_code != 0 {
+        exit(exit_code);
+    }
+}
+
+fn serialize_error<S: Serializer>(
+    error: &Option<FormatFailure>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match error {
+        Some(error) => serializer.collect_str(error),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn collect_file_stats(path: PathBuf, settings: &FormatterSettings) -> FileStats {
+    let start = Instant::now();
+    let result = fs::read_to_string(&path)
+        .map_err(FormatFailure::from)
+        .and_then(|source| format_source_with(|| view_macro_stats(&source, settings)));
+    let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    let (macros, error) = match result {
+        Ok(macros) => (macros, None),
+        Err(err) => (Vec::new(), Some(err)),
+    };
+
+    FileStats {
+        path,
+        duration_ms,
+        error,
+        macros,
+    }
+}
+
+fn print_report(report: &StatsReport) {
+    println!(
+        "{:<50}{:>8}{:>8}{:>8}{:>8}{:>18}",
+        "Location", "Lines", "Nodes", "Depth", "Attrs", "Needs formatting"
+    );
+    for file in &report.file_stats {
+        if let Some(err) = &file.error {
+            println!("{:<50}  ❌ {err}", file.path.display());
+            continue;
+        }
+
+        for mac in &file.macros {
+            println!(
+                "{:<50}{:>8}{:>8}{:>8}{:>8}{:>18}",
+                location(&file.path, mac),
+                mac.lines,
+                mac.nodes,
+                mac.depth,
+                mac.attributes,
+                if mac.needs_formatting { "yes" } else { "no" }
+            );
+        }
+    }
+
+    println!();
+    println!(
+        "{} of {} view macros in {} of {} files would change",
+        report.macros_needing_formatting,
+        report.macros,
+        report.files_needing_formatting,
+        report.files
+    );
+
+    if !report.slowest_files.is_empty() {
+        println!();
+        println!("Slowest files:");
+        for file in &report.slowest_files {
+            println!("{:>10.2} ms  {}", file.duration_ms, file.path.display());
+        }
+    }
+}
+
+fn location(path: &Path, mac: &ViewMacroStats) -> String {
+    format!("{}:{}:{}", path.display(), mac.line, mac.column)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    #[test]
+    fn json_report() {
+        let file = |path: &str, error, macros| FileStats {
+            path: PathBuf::from(path),
+            duration_ms: 1.5,
+            error,
+            macros,
+        };
+
+        let report = StatsReport {
+            files: 2,
+            files_needing_formatting: 1,
+            macros: 1,
+            macros_needing_formatting: 1,
+            slowest_files: vec![FileTiming {
+                path: PathBuf::from("src/a.rs"),
+                duration_ms: 1.5,
+            }],
+            file_stats: vec![
+                file(
+                    "src/a.rs",
+                    None,
+                    vec![ViewMacroStats {
+                        line: 3,
+                        column: 5,
+                        lines: 7,
+                        nodes: 5,
+                        depth: 3,
+                        attributes: 3,
+                        needs_formatting: true,
+                    }],
+                ),
+                file(
+                    "src/b.rs",
+                    Some(FormatFailure::Io(io::Error::other("denied"))),
+                    Vec::new(),
+                ),
+            ],
+        };
+
+        let expected = r#"{
+  "files": 2,
+  "files_needing_formatting": 1,
+  "macros": 1,
+  "macros_needing_formatting": 1,
+  "slowest_files": [
+    {
+      "path": "src/a.rs",
+      "duration_ms": 1.5
+    }
+  ],
+  "file_stats": [
+    {
+      "path": "src/a.rs",
+      "duration_ms": 1.5,
+      "macros": [
+        {
+          "line": 3,
+          "column": 5,
+          "lines": 7,
+          "nodes": 5,
+          "depth": 3,
+          "attributes": 3,
+          "needs_formatting": true
+        }
+      ]
+    },
+    {
+      "path": "src/b.rs",
+      "duration_ms": 1.5,
+      "error": "could not read or write file: denied",
+      "macros": []
+    }
+  ]
+}"#;
+        assert_eq!(serde_json::to_string_pretty(&report).unwrap(), expected);
+    }
+}
@@ -1,57 +1,456 @@
+use std::collections::HashSet;
+
 use crop::Rope;
+use proc_macro2::{Delimiter, LineColumn, TokenStream, TokenTree};
 use syn::{
     spanned::Spanned,
     visit::{self, Visit},
-    File, Macro,
+    Attribute, Expr, File, Macro, Stmt,
 };
 
-use crate::{view_macro::get_macro_full_path, ParentIndent, ViewMacro};
+use crate::{
+    display_width, view_macro::get_macro_full_path, FormatterSettings, ParentIndent, ViewMacro,
+};
 
 struct ViewMacroVisitor<'a> {
     macros: Vec<ViewMacro<'a>>,
     source: Rope,
-    macro_names: &'a Vec<String>,
+    settings: &'a FormatterSettings,
+    statement_macros: StatementMacros,
+}
+
+/// Comment that excludes the node, attribute or macro below it from formatting
+pub(crate) const SKIP_COMMENT: &str = "leptosfmt::skip";
+
+/// Comment that excludes the whole file from formatting
+const SKIP_FILE_COMMENT: &str = "leptosfmt::skip-file";
+
+/// Attributes that exclude the item (and every view macro inside of it) from formatting
+const SKIP_ATTRIBUTES: [&str; 2] = ["rustfmt::skip", "leptosfmt::skip"];
+
+// Items, statements and expressions marked with a skip attribute are not visited, so view macros within them are left untouched
+macro_rules! skip_attributed {
+    ($($visit:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $visit(&mut self, node: &'ast $ty) {
+                if !has_skip_attribute(&node.attrs) {
+                    visit::$visit(self, node);
+                }
+            }
+        )*
+    };
 }
 
 impl<'ast> Visit<'ast> for ViewMacroVisitor<'ast> {
+    skip_attributed! {
+        visit_file(syn::File),
+        visit_item_const(syn::ItemConst),
+        visit_item_enum(syn::ItemEnum),
+        visit_item_fn(syn::ItemFn),
+        visit_item_impl(syn::ItemImpl),
+        visit_item_macro(syn::ItemMacro),
+        visit_item_mod(syn::ItemMod),
+        visit_item_static(syn::ItemStatic),
+        visit_item_struct(syn::ItemStruct),
+        visit_item_trait(syn::ItemTrait),
+        visit_item_type(syn::ItemType),
+        visit_impl_item_const(syn::ImplItemConst),
+        visit_impl_item_fn(syn::ImplItemFn),
+        visit_impl_item_macro(syn::ImplItemMacro),
+        visit_trait_item_const(syn::TraitItemConst),
+        visit_trait_item_fn(syn::TraitItemFn),
+        visit_trait_item_macro(syn::TraitItemMacro),
+        visit_local(syn::Local),
+        visit_stmt_macro(syn::StmtMacro),
+        visit_arm(syn::Arm),
+        visit_field_value(syn::FieldValue),
+        visit_variant(syn::Variant),
+        visit_expr_array(syn::ExprArray),
+        visit_expr_assign(syn::ExprAssign),
+        visit_expr_async(syn::ExprAsync),
+        visit_expr_await(syn::ExprAwait),
+        visit_expr_binary(syn::ExprBinary),
+        visit_expr_block(syn::ExprBlock),
+        visit_expr_break(syn::ExprBreak),
+        visit_expr_call(syn::ExprCall),
+        visit_expr_cast(syn::ExprCast),
+        visit_expr_closure(syn::ExprClosure),
+        visit_expr_const(syn::ExprConst),
+        visit_expr_field(syn::ExprField),
+        visit_expr_for_loop(syn::ExprForLoop),
+        visit_expr_group(syn::ExprGroup),
+        visit_expr_if(syn::ExprIf),
+        visit_expr_index(syn::ExprIndex),
+        visit_expr_let(syn::ExprLet),
+        visit_expr_loop(syn::ExprLoop),
+        visit_expr_macro(syn::ExprMacro),
+        visit_expr_match(syn::ExprMatch),
+        visit_expr_method_call(syn::ExprMethodCall),
+        visit_expr_paren(syn::ExprParen),
+        visit_expr_range(syn::ExprRange),
+        visit_expr_reference(syn::ExprReference),
+        visit_expr_repeat(syn::ExprRepeat),
+        visit_expr_return(syn::ExprReturn),
+        visit_expr_struct(syn::ExprStruct),
+        visit_expr_try(syn::ExprTry),
+        visit_expr_try_block(syn::ExprTryBlock),
+        visit_expr_tuple(syn::ExprTuple),
+        visit_expr_unary(syn::ExprUnary),
+        visit_expr_unsafe(syn::ExprUnsafe),
+        visit_expr_while(syn::ExprWhile),
+        visit_expr_yield(syn::ExprYield),
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        self.statement_macros.record_stmt(stmt);
+        visit::visit_stmt(self, stmt);
+    }
+
     fn visit_macro(&mut self, node: &'ast Macro) {
         let should_format = self
+            .settings
             .macro_names
             .iter()
-            .any(|macro_name| &get_macro_full_path(node) == macro_name);
+            .any(|macro_name| &get_macro_full_path(node) == macro_name)
+            && !has_skip_comment(&self.source, node);
 
         if should_format {
-            let span_line = node.span().start().line;
-            let line = self.source.line(span_line - 1);
+            let parent_indent = parent_indent(&self.source, node);
+            if let Some(mut view_mac) =
+                ViewMacro::try_parse(parent_indent, node, &self.settings.parser)
+            {
+                view_mac.keep_delimiter = self.statement_macros.contains(node);
+                self.macros.push(view_mac);
+            }
+        }
 
-            let indent_chars: Vec<_> = line
-                .chars()
-                .take_while(|&c| c == ' ' || c == '\t')
-                .collect();
+        visit::visit_macro(self, node);
+    }
+}
 
-            let tabs = indent_chars.iter().filter(|&&c| c == '\t').count();
-            let spaces = indent_chars.iter().filter(|&&c| c == ' ').count();
+/// The macros that start an expression statement without being the whole statement (e.g. `view!(..)[0];`).
+/// A macro with braces ends the statement in that position, so these macros have to keep their delimiter.
+#[derive(Default)]
+pub(crate) struct StatementMacros(HashSet<LineColumn>);
 
-            if let Some(view_mac) = ViewMacro::try_parse(ParentIndent { tabs, spaces }, node) {
-                self.macros.push(view_mac);
+impl StatementMacros {
+    pub(crate) fn contains(&self, mac: &Macro) -> bool {
+        self.0.contains(&mac.span().start())
+    }
+
+    fn record_stmt(&mut self, stmt: &Stmt) {
+        if let Stmt::Expr(expr, _) = stmt {
+            self.record_expr(expr);
+        }
+    }
+
+    /// Records the macro at the start of an expression that is (or may be) in statement position
+    pub(crate) fn record_expr(&mut self, expr: &Expr) {
+        if let Some(mac) = leading_macro(expr) {
+            self.0.insert(mac.span().start());
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for StatementMacros {
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        self.record_stmt(stmt);
+        visit::visit_stmt(self, stmt);
+    }
+}
+
+/// The macro an expression starts with, if braces around it would end the statement.
+/// A `.` or `?` right after the braces continues the statement (e.g. `view! { .. }.into_any();`).
+fn leading_macro(expr: &Expr) -> Option<&Macro> {
+    let (inner, continues_statement) = match expr {
+        Expr::MethodCall(expr) => (&*expr.receiver, true),
+        Expr::Field(expr) => (&*expr.base, true),
+        Expr::Await(expr) => (&*expr.base, true),
+        Expr::Try(expr) => (&*expr.expr, true),
+        Expr::Index(expr) => (&*expr.expr, false),
+        Expr::Cast(expr) => (&*expr.expr, false),
+        Expr::Call(expr) => (&*expr.func, false),
+        Expr::Binary(expr) => (&*expr.left, false),
+        Expr::Assign(expr) => (&*expr.left, false),
+        Expr::Range(expr) => (expr.start.as_deref()?, false),
+        _ => return None,
+    };
+
+    match inner {
+        Expr::Macro(_) if continues_statement => None,
+        Expr::Macro(expr) => Some(&expr.mac),
+        inner => leading_macro(inner),
+    }
+}
+
+fn has_skip_attribute(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let path = attr
+            .path()
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>()
+            .join("::");
+
+        SKIP_ATTRIBUTES.contains(&path.as_str())
+    })
+}
+
+pub(crate) fn line_comment(line: &str) -> Option<&str> {
+    line.trim().strip_prefix("//").map(str::trim)
+}
+
+/// Whether the comment lines directly above the macro contain a `// leptosfmt::skip` comment
+pub(crate) fn has_skip_comment(source: &Rope, mac: &Macro) -> bool {
+    let macro_line = mac.span().start().line - 1;
+    (0..macro_line)
+        .rev()
+        .map(|line| source.line(line).to_string())
+        .map_while(|line| line_comment(&line).map(ToOwned::to_owned))
+        .any(|comment| comment == SKIP_COMMENT)
+}
+
+/// Whether the comments at the top of the file, before the first item, contain a `// leptosfmt::skip-file` comment
+pub(crate) fn has_skip_file_comment(source: &str) -> bool {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("#!"))
+        .map_while(line_comment)
+        .any(|comment| comment == SKIP_FILE_COMMENT)
+}
+
+pub(crate) fn parent_indent(source: &Rope, mac: &Macro) -> ParentIndent {
+    let start = mac.span().start();
+    let line = source.line(start.line - 1);
+
+    let indent_chars: Vec<_> = line
+        .chars()
+        .take_while(|&c| c == ' ' || c == '\t')
+        .collect();
+
+    let tabs = indent_chars.iter().filter(|&&c| c == '\t').count();
+    let spaces = indent_chars.iter().filter(|&&c| c == ' ').count();
+
+    let prefix: String = line
+        .chars()
+        .take(start.column)
+        .skip(indent_chars.len())
+        .collect();
+
+    // Trailing comments may run past the maximum width
+    let end = mac.delimiter.span().close().end();
+    let suffix: String = source.line(end.line - 1).chars().skip(end.column).collect();
+    let suffix = strip_line_comment(&suffix).trim_end();
+
+    ParentIndent {
+        prefix_width: display_width(&prefix),
+        suffix_width: display_width(suffix),
+        ..ParentIndent::new(tabs, spaces)
+    }
+}
+
+/// The code of a line before its `//` comment, `//` within string and char literals does not start a comment
+fn strip_line_comment(line: &str) -> &str {
+    let mut rest = line;
+    while let Some(idx) = rest.find(['/', '"', '\'']) {
+        let (code, tail) = rest.split_at(idx);
+        rest = match tail.as_bytes()[0] {
+            b'/' if tail.starts_with("//") => return &line[..line.len() - tail.len()],
+            b'"' => {
+                // Raw strings (e.g. `r#"..."#`) end at a quote followed by the same number of hashes
+                let before_hashes = code.trim_end_matches('#');
+                let raw = before_hashes.ends_with('r');
+                let hashes = code.len() - before_hashes.len();
+                skip_string(&tail[1..], raw.then_some(hashes))
             }
+            b'\'' => skip_char(&tail[1..]),
+            _ => &tail[1..],
+        };
+    }
+    line
+}
+
+/// Skips the rest of a string literal, a string that does not end on the line takes up the rest of it
+fn skip_string(rest: &str, raw_hashes: Option<usize>) -> &str {
+    if let Some(hashes) = raw_hashes {
+        let end = format!("\"{}", "#".repeat(hashes));
+        return rest.find(&end).map_or("", |idx| &rest[idx + end.len()..]);
+    }
+
+    let mut chars = rest.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '"' => return &rest[idx + 1..],
+            _ => {}
         }
+    }
+    ""
+}
 
-        visit::visit_macro(self, node);
+/// Skips the rest of a char literal (e.g. `'/'` or `'\''`), lifetimes (e.g. `'a`) are left as is
+fn skip_char(rest: &str) -> &str {
+    let mut chars = rest.char_indices();
+    match chars.next() {
+        Some((_, '\\')) => {
+            chars.next();
+            chars
+                .find(|(_, c)| *c == '\'')
+                .map_or("", |(idx, _)| &rest[idx + 1..])
+        }
+        Some((_, c)) if rest[c.len_utf8()..].starts_with('\'') => &rest[c.len_utf8() + 1..],
+        _ => rest,
     }
 }
 
 pub fn collect_macros_in_file<'a>(
     file: &'a File,
     source: Rope,
-    macro_names: &'a Vec<String>,
+    settings: &'a FormatterSettings,
 ) -> (Rope, Vec<ViewMacro<'a>>) {
     let mut visitor = ViewMacroVisitor {
         source,
         macros: Vec::new(),
-        macro_names,
+        settings,
+        statement_macros: StatementMacros::default(),
     };
 
     visitor.visit_file(file);
     (visitor.source, visitor.macros)
 }
+
+/// Collects macro invocations straight from a token stream, without requiring the surrounding code to be valid Rust.
+pub fn collect_macros_in_tokens(tokens: TokenStream, macro_names: &[String]) -> Vec<Macro> {
+    let mut macros = Vec::new();
+    collect_macro_invocations(tokens, macro_names, &mut macros);
+    macros
+}
+
+fn collect_macro_invocations(tokens: TokenStream, macro_names: &[String], macros: &mut Vec<Macro>) {
+    let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+    // Set after a skip attribute, until the end of the item or statement it is attached to
+    let mut skip_item = false;
+
+    for (idx, token) in tokens.iter().enumerate() {
+        let group = match token {
+            TokenTree::Punct(punct) if punct.as_char() == ';' => {
+                skip_item = false;
+                continue;
+            }
+            TokenTree::Group(group) => group,
+            _ => continue,
+        };
+
+        match skip_attribute(&tokens[..=idx]) {
+            // An inner attribute (`#![rustfmt::skip]`) skips the rest of the enclosing item
+            Some(true) => return,
+            Some(false) => {
+                skip_item = true;
+                continue;
+            }
+            None => {}
+        }
+
+        if skip_item {
+            // Besides a `;`, the item or statement ends with a body that is followed by the next item or statement,
+            // other braces are part of it (e.g. `Foo { a }.render(..)`, `Foo<{ N }>` or `if .. { .. } else { .. }`)
+            let ends_item = match tokens.get(idx + 1) {
+                None => true,
+                Some(TokenTree::Ident(ident)) => ident != "else",
+                Some(TokenTree::Punct(punct)) => punct.as_char() == '#',
+                _ => false,
+            };
+            if group.delimiter() == Delimiter::Brace && ends_item {
+                skip_item = false;
+            }
+            continue;
+        }
+
+        let mac = macro_path_start(&tokens[..idx])
+            .map(|start| tokens[start..=idx].iter().cloned().collect::<TokenStream>())
+            .and_then(|stream| syn::parse2::<Macro>(stream).ok());
+
+        // The bodies of other macros are not visited, the same as when collecting from a syntax tree
+        match mac {
+            Some(mac) if macro_names.contains(&get_macro_full_path(&mac)) => macros.push(mac),
+            Some(_) => {}
+            None if is_macro_rules_body(&tokens[..idx]) => {}
+            None => collect_macro_invocations(group.stream(), macro_names, macros),
+        }
+    }
+}
+
+/// Whether the tokens preceding a group are `macro_rules! name`
+fn is_macro_rules_body(tokens: &[TokenTree]) -> bool {
+    matches!(
+        tokens,
+        [.., TokenTree::Ident(macro_rules), TokenTree::Punct(bang), TokenTree::Ident(_)]
+            if macro_rules == "macro_rules" && bang.as_char() == '!'
+    )
+}
+
+/// Whether the tokens end with a `#[rustfmt::skip]` or `#[leptosfmt::skip]` attribute,
+/// returns `Some(true)` for an inner attribute (`#![rustfmt::skip]`)
+fn skip_attribute(tokens: &[TokenTree]) -> Option<bool> {
+    let (inner, attribute) = match tokens {
+        [.., TokenTree::Punct(hash), TokenTree::Punct(bang), TokenTree::Group(attribute)]
+            if hash.as_char() == '#' && bang.as_char() == '!' =>
+        {
+            (true, attribute)
+        }
+        [.., TokenTree::Punct(hash), TokenTree::Group(attribute)] if hash.as_char() == '#' => {
+            (false, attribute)
+        }
+        _ => return None,
+    };
+
+    if attribute.delimiter() != Delimiter::Bracket {
+        return None;
+    }
+
+    let path: String = attribute
+        .stream()
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) => Some(ident.to_string()),
+            TokenTree::Punct(punct) if punct.as_char() == ':' => Some(":".to_string()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    SKIP_ATTRIBUTES.contains(&path.as_str()).then_some(inner)
+}
+
+/// Returns the index of the first token of a macro path (e.g. `leptos::view!` or `::leptos::view!`), given the tokens preceding a group.
+fn macro_path_start(tokens: &[TokenTree]) -> Option<usize> {
+    let [.., TokenTree::Ident(_), TokenTree::Punct(bang)] = tokens else {
+        return None;
+    };
+
+    if bang.as_char() != '!' {
+        return None;
+    }
+
+    let mut start = tokens.len() - 2;
+    while let [.., TokenTree::Ident(_), TokenTree::Punct(first), TokenTree::Punct(second)] =
+        &tokens[..start]
+    {
+        if first.as_char() != ':' || second.as_char() != ':' {
+            break;
+        }
+        start -= 3;
+    }
+
+    // Absolute path, e.g. `::leptos::view!`
+    if let [.., TokenTree::Punct(first), TokenTree::Punct(second)] = &tokens[..start] {
+        if first.as_char() == ':' && second.as_char() == ':' {
+            start -= 2;
+        }
+    }
+
+    Some(start)
+}
@@ -3,17 +3,96 @@ use rstml::node::{
 };
 use syn::{spanned::Spanned, Expr, RangeLimits, Stmt};
 
-use crate::{formatter::Formatter, AttributeValueBraceStyle as Braces};
+use crate::{formatter::Formatter, AttributeGroupOrder, AttributeValueBraceStyle as Braces};
 
 use super::ExpressionFormatter;
 
 impl Formatter<'_> {
     pub fn attribute(&mut self, attribute: &NodeAttribute, next_attribute: Option<&NodeAttribute>) {
-        self.flush_comments(attribute.span().start().line - 1, false);
-        match attribute {
-            NodeAttribute::Attribute(k) => self.keyed_attribute(k, next_attribute),
-            NodeAttribute::Block(b) => self.node_block(b),
+        let span = attribute.span();
+        let skip = self.is_skipped(span.start());
+        self.leading_trivia(span.start(), false);
+
+        if skip {
+            self.skipped(attribute);
+        } else {
+            match attribute {
+                NodeAttribute::Block(b @ NodeBlock::Invalid(_)) => self.skipped(b),
+                NodeAttribute::Attribute(k) => {
+                    self.hoist_comments(k);
+                    self.keyed_attribute(k, next_attribute);
+                }
+                NodeAttribute::Block(b) => {
+                    self.hoist_comments(b);
+                    self.node_block(b);
+                }
+            }
+        }
+
+        self.trailing_comment(span.end());
+    }
+
+    /// Orders attributes by the configured `attr_order` groups.
+    /// Block attributes (e.g. `{..}` spreads) keep their position, only the attributes in between them are reordered.
+    pub(crate) fn ordered_attributes<'b>(
+        &self,
+        attributes: &'b [NodeAttribute],
+    ) -> Vec<&'b NodeAttribute> {
+        let mut ordered: Vec<_> = attributes.iter().collect();
+        if self.settings.attr_order.is_empty() {
+            return ordered;
+        }
+
+        for segment in ordered.split_mut(|attr| matches!(attr, NodeAttribute::Block(_))) {
+            segment.sort_by_cached_key(|attr| {
+                let name = match attr {
+                    NodeAttribute::Attribute(attr) => attr.key.to_string(),
+                    NodeAttribute::Block(_) => unreachable!("segments do not contain blocks"),
+                };
+
+                let group = self.attribute_group(&name);
+                match self.settings.attr_group_order {
+                    AttributeGroupOrder::AsWritten => (group, None),
+                    AttributeGroupOrder::Alphabetical => (group, Some(name)),
+                }
+            });
+        }
+
+        ordered
+    }
+
+    /// Finds the group of an attribute: an exact name match wins over the longest matching `prefix*` pattern,
+    /// which wins over the `*` wildcard. Attributes that do not match any group are placed after all groups.
+    fn attribute_group(&self, name: &str) -> usize {
+        let groups = &self.settings.attr_order;
+        let mut prefix_match: Option<(usize, usize)> = None;
+        let mut wildcard_match = None;
+
+        for (group_idx, group) in groups.iter().enumerate() {
+            for pattern in group {
+                if pattern == name {
+                    return group_idx;
+                }
+
+                match pattern.strip_suffix('*') {
+                    Some("") => {
+                        wildcard_match.get_or_insert(group_idx);
+                    }
+                    Some(prefix)
+                        if name.starts_with(prefix)
+                            && prefix_match.is_none_or(|(len, _)| prefix.len() > len) =>
+                    {
+                        prefix_match = Some((prefix.len(), group_idx));
+                    }
+                    _ => {}
+                }
+            }
         }
+
+        prefix_match
+            .map(|(_, group_idx)| group_idx)
+            .or(wildcard_match)
+            .unwrap_or(groups.len())
     }
 
     fn keyed_attribute(
@@ -59,9 +138,7 @@ impl Formatter<'_> {
         next_attribute: Option<&NodeAttribute>,
     ) {
         match value {
-            KVAttributeValue::InvalidBraced(invalid) => {
-                self.printer.word(invalid.span().source_text().unwrap());
-            }
+            KVAttributeValue::InvalidBraced(invalid) => self.source_code(invalid),
             KVAttributeValue::Expr(expr) => {
                 match (self.settings.attr_value_brace_style, expr, next_attribute) {
                     (Braces::WhenRequired, syn::Expr::Block(_), Some(next))
@@ -71,6 +148,15 @@ impl Formatter<'_> {
                         // to avoid an ambiguity in the parser (i.e. `foo=bar {..}` could be interpreted as initialization of a struct called `bar`, instead of two separate attributes)
                         self.node_value_expr(expr, false, true, formatter)
                     }
+                    (Braces::WhenRequired | Braces::Preserve, _, Some(next))
+                        if !matches!(expr, syn::Expr::Block(_) | syn::Expr::Lit(_))
+                            && is_spread_attribute(next) =>
+                    {
+                        // Attributes may be reordered in front of a spread attribute, `on:click=a {..}` would be parsed as a struct literal
+                        self.printer.word("{");
+                        self.node_value_expr(expr, false, false, formatter);
+                        self.printer.word("}");
+                    }
                     (Braces::Always, syn::Expr::Block(_), _) => {
                         self.node_value_expr(expr, false, false, formatter)
                     }
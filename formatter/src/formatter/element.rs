@@ -1,8 +1,11 @@
-use crate::{formatter::Formatter, ClosingTagStyle};
+use crate::{
+    formatter::Formatter, get_text_beween_spans, trivia::Trivia, AttributeWrap, ChildrenLayout,
+    ClosingTagStyle, WhitespaceSensitivity,
+};
 
+use proc_macro2::LineColumn;
 use rstml::{
-    atoms::OpenTag,
-    node::{Node, NodeAttribute, NodeElement},
+    node::{Node, NodeAttribute, NodeElement, NodeName},
     Infallible,
 };
 use syn::spanned::Spanned;
@@ -10,90 +13,284 @@ use syn::spanned::Spanned;
 impl Formatter<'_> {
     pub fn element(&mut self, element: &NodeElement<Infallible>) {
         let name = element.name().to_string();
-        let is_self_closing = is_self_closing(element, &name, self.settings.closing_tag_style);
+        let closing_tag_style = match self.settings.component_closing_tag_style {
+            Some(style) if is_component(element.name()) => style,
+            _ => self.settings.closing_tag_style,
+        };
+        // Comments before the closing tag keep the closing tag
+        let dangling = match &element.close_tag {
+            Some(close_tag) => self.closing_tag_trivia(close_tag.span()),
+            None => Vec::new(),
+        };
+        let is_self_closing =
+            dangling.is_empty() && is_self_closing(element, &name, closing_tag_style);
 
         self.opening_tag(element, is_self_closing);
 
         if !is_self_closing {
-            self.children(&element.children, element.attributes().len());
-            self.flush_comments(element.close_tag.span().end().line - 1, true);
-            // Note: we pass open_tag instead of close_tag, such that we may auto-close non-self-closing elements
-            self.closing_tag(&element.open_tag);
+            if !self.verbatim_children(element) {
+                self.trailing_comment(element.open_tag.span().end());
+                self.children(&element.children, element.attributes().len(), dangling);
+            }
+            self.closing_tag(element);
         }
     }
 
     fn opening_tag(&mut self, element: &NodeElement<Infallible>, is_self_closing: bool) {
+        let open_tag = &element.open_tag;
+        let name_end = if open_tag.generics.params.is_empty() {
+            open_tag.name.span().end()
+        } else {
+            open_tag.generics.span().end()
+        };
+
+        // Comments within the tag name are printed before the tag
+        self.hoist_comments_between(open_tag.span().start(), name_end, open_tag);
+
         self.printer.word("<");
-        self.node_name(&element.open_tag.name);
-        self.format_syn_generics(&element.open_tag.generics);
+        self.node_name(&open_tag.name);
+        self.format_syn_generics(&open_tag.generics);
+        self.trailing_comment(name_end);
+
+        // Comments after the last attribute, before `>` or `/>`
+        let attributes_end = element
+            .attributes()
+            .iter()
+            .map(|attribute| attribute.span().end())
+            .max()
+            .unwrap_or(name_end);
+        // The comment at the end of the line of the last attribute trails that attribute
+        let after_attributes = LineColumn {
+            column: attributes_end.column + 1,
+            ..attributes_end
+        };
+        let dangling =
+            self.trivia
+                .take_within(after_attributes, open_tag.end_tag.span().end(), &[]);
 
-        self.attributes(element.attributes(), is_self_closing);
+        self.attributes(element.attributes(), is_self_closing, dangling);
 
         if is_self_closing {
             self.printer.word("/>");
+            self.trailing_comment(open_tag.span().end());
         } else {
             self.printer.word(">")
         }
     }
 
-    fn closing_tag(&mut self, open_tag: &OpenTag) {
+    /// Prints the children of `preserve_elements` exactly as written in the source.
+    /// Returns false if the children have to be formatted instead (e.g. when there is no source available).
+    fn verbatim_children(&mut self, element: &NodeElement<Infallible>) -> bool {
+        let (Some(source), Some(close_tag)) = (self.source, &element.close_tag) else {
+            return false;
+        };
+
+        if !self
+            .settings
+            .preserve_elements
+            .contains(&element.name().to_string())
+        {
+            return false;
+        }
+
+        let start = element.open_tag.span().end();
+        let end = close_tag.span().start();
+        let children = get_text_beween_spans(source, start, end).to_string();
+
+        // The indentation before the closing tag is not part of the content,
+        // the closing tag is indented like the opening tag instead
+        let (content, closing_line) = match children.rsplit_once('\n') {
+            Some((content, indent)) if indent.trim().is_empty() => (content, true),
+            _ => (children.as_str(), false),
+        };
+
+        // Every line is a word of its own, so that only the width of the last line counts towards the line it ends on
+        let mut lines = content.split('\n').peekable();
+        while let Some(line) = lines.next() {
+            // The printer prints its own line endings
+            self.printer.word(line.trim_end_matches('\r').to_owned());
+            if lines.peek().is_some() {
+                self.printer.hardbreak_without_indent();
+            }
+        }
+        if closing_line {
+            self.printer.hardbreak();
+        }
+
+        self.drop_trivia(start, end);
+        true
+    }
+
+    fn closing_tag(&mut self, element: &NodeElement<Infallible>) {
+        // Keep the `</_>` wildcard, the open tag may not have a name that can be repeated (e.g. `<{..}>`)
+        if let Some(close_tag) = &element.close_tag {
+            if close_tag.name.is_wildcard() {
+                self.printer.word("</_>");
+                return;
+            }
+        }
+
+        // Note: we use open_tag instead of close_tag, such that we may auto-close non-self-closing elements
+        let open_tag = &element.open_tag;
         self.printer.word("</");
         self.node_name(&open_tag.name);
         self.format_syn_generics(&open_tag.generics);
         self.printer.word(">");
     }
 
-    fn attributes(&mut self, attributes: &[NodeAttribute], trailing_space: bool) {
-        match attributes {
-            [] => {
+    fn attributes(
+        &mut self,
+        attributes: &[NodeAttribute],
+        trailing_space: bool,
+        dangling: Vec<String>,
+    ) {
+        let attributes = self.ordered_attributes(attributes);
+        match attributes.as_slice() {
+            [] if dangling.is_empty() => {
                 if trailing_space {
                     self.printer.nbsp();
                 }
             }
-            [attribute] => {
+            // Comments before an attribute need the attributes on their own lines
+            attributes
+                if dangling.is_empty()
+                    && !attributes
+                        .iter()
+                        .any(|attribute| self.has_own_line_comments(*attribute))
+                    && (attributes.len() == 1
+                        || self.settings.attr_wrap == AttributeWrap::Never) =>
+            {
                 self.printer.cbox(0);
-                self.printer.nbsp();
-                self.attribute(attribute, None);
+
+                let mut iter = attributes.iter().peekable();
+                while let Some(attr) = iter.next() {
+                    self.printer.nbsp();
+                    self.attribute(attr, iter.peek().copied().copied());
+                }
 
                 if trailing_space {
                     self.printer.nbsp();
                 }
                 self.printer.end();
             }
-            _ => {
+            attributes => {
+                let force_wrap = self.force_attribute_wrap(attributes);
+
                 self.printer.cbox_indent();
-                self.printer.space();
 
                 let mut iter = attributes.iter().peekable();
                 while let Some(attr) = iter.next() {
-                    self.attribute(attr, iter.peek().copied());
+                    self.attribute_break(force_wrap);
+                    self.attribute(attr, iter.peek().copied().copied());
+                }
 
-                    if iter.peek().is_some() {
-                        self.printer.space()
-                    }
+                for comment in &dangling {
+                    self.printer.hardbreak();
+                    self.comment_lines(comment);
                 }
 
-                if trailing_space {
-                    self.printer.space(); // Only results in a space if the consistent box didn't break
+                // `>` cannot follow a `//` comment on the same line
+                if dangling.is_empty() && self.bracket_same_line(trailing_space) {
+                    if trailing_space {
+                        self.printer.nbsp();
+                    }
+                    self.printer.end();
                 } else {
-                    self.printer.zerobreak();
+                    if trailing_space {
+                        self.printer.space(); // Only results in a space if the consistent box didn't break
+                    } else {
+                        self.printer.zerobreak();
+                    }
+                    self.printer.end_dedent();
                 }
-                self.printer.end_dedent();
             }
         }
     }
 
-    pub fn children(&mut self, children: &[Node], attribute_count: usize) {
-        if children.is_empty() {
-            return;
+    /// Whether `>` (or `/>` for self-closing tags) stays on the line of the last attribute when the attributes break
+    fn bracket_same_line(&self, is_self_closing: bool) -> bool {
+        if is_self_closing {
+            self.settings.self_closing_bracket_same_line
+        } else {
+            self.settings.bracket_same_line
         }
+    }
 
-        let is_textual = children
-            .first()
-            .map(|n| matches!(n, Node::Text(_) | Node::RawText(_) | Node::Block(_)))
-            .unwrap_or_default();
+    /// Whether every attribute has to go on its own line, regardless of the available width
+    fn force_attribute_wrap(&self, attributes: &[&NodeAttribute]) -> bool {
+        match self.settings.attr_wrap {
+            AttributeWrap::Always => true,
+            AttributeWrap::Never => false,
+            AttributeWrap::Auto => self
+                .settings
+                .max_attrs_per_line
+                .is_some_and(|max| attributes.len() > max),
+        }
+    }
+
+    fn attribute_break(&mut self, force_wrap: bool) {
+        if force_wrap {
+            self.printer.hardbreak();
+        } else {
+            self.printer.space();
+        }
+    }
+
+    /// Whether children are filled onto as few lines as possible, instead of putting every child on its own line
+    fn fill_children(&self, children: &[Node], attribute_count: usize) -> bool {
+        if self
+            .settings
+            .max_children_per_line
+            .is_some_and(|max| children.len() > max)
+        {
+            return false;
+        }
+
+        match self.settings.children_layout {
+            ChildrenLayout::Fill => true,
+            ChildrenLayout::OnePerLine => false,
+            ChildrenLayout::Auto => {
+                // Text mixed with inline elements (e.g. a paragraph with links) is filled,
+                // block elements get a line of their own
+                children.iter().any(is_textual)
+                    && children
+                        .iter()
+                        .all(|child| is_textual(child) || self.is_inline_element(child))
+                    && attribute_count <= 1
+            }
+        }
+    }
+
+    fn is_inline_element(&self, node: &Node) -> bool {
+        let Node::Element(element) = node else {
+            return false;
+        };
+
+        let name = element.name().to_string();
+        self.settings.inline_elements.contains(&name)
+    }
+
+    /// Whether adding or removing whitespace between two siblings could change the rendered output
+    fn is_whitespace_sensitive(&self, node: &Node, next_node: &Node) -> bool {
+        let is_inline = |node| is_textual(node) || self.is_inline_element(node);
 
-        let soft_break = is_textual && attribute_count <= 1;
+        match self.settings.whitespace_sensitivity {
+            WhitespaceSensitivity::Ignore => false,
+            WhitespaceSensitivity::Strict => is_inline(node) && is_inline(next_node),
+        }
+    }
+
+    pub(crate) fn children(
+        &mut self,
+        children: &[Node],
+        attribute_count: usize,
+        dangling: Vec<Trivia>,
+    ) {
+        if children.is_empty() && dangling.is_empty() {
+            return;
+        }
+
+        let soft_break = dangling.is_empty() && self.fill_children(children, attribute_count);
 
         if soft_break {
             self.printer.cbox_indent();
@@ -105,9 +302,10 @@ impl Formatter<'_> {
             self.printer.hardbreak();
         }
 
+        let mut previous = None;
         let mut iter = children.iter().peekable();
         while let Some(child) = iter.next() {
-            self.node(child);
+            self.child_node(child, previous.replace(child), iter.peek().copied());
 
             if let Some(next_child) = iter.peek() {
                 let curr_end = child.span().end();
@@ -115,14 +313,31 @@ impl Formatter<'_> {
                 let consecutive =
                     curr_end.line == next_start.line && next_start.column == curr_end.column;
 
-                if !matches!(next_child, Node::RawText(_)) && !consecutive {
+                if self.is_whitespace_sensitive(child, next_child) {
+                    // Only break where the source already has whitespace, so the rendered text stays the same
+                    if !consecutive {
+                        self.printer.space()
+                    }
+                    continue;
+                } else if !matches!(next_child, Node::RawText(_)) && !consecutive {
                     self.printer.space()
                 } else {
                     self.printer.zerobreak()
                 }
+
+                if !soft_break {
+                    self.separate_siblings();
+                }
             }
         }
 
+        if !dangling.is_empty() {
+            if !children.is_empty() {
+                self.printer.hardbreak();
+            }
+            self.dangling_trivia(dangling);
+        }
+
         if soft_break {
             self.printer.end();
             self.printer.zerobreak();
@@ -134,24 +349,37 @@ impl Formatter<'_> {
     }
 }
 
+fn is_textual(node: &Node) -> bool {
+    matches!(node, Node::Text(_) | Node::RawText(_) | Node::Block(_))
+}
+
+pub(crate) const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+pub(crate) const INLINE_ELEMENTS: [&str; 25] = [
+    "a", "abbr", "b", "bdi", "bdo", "br", "cite", "code", "data", "dfn", "em", "i", "kbd", "mark",
+    "q", "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var",
+];
+
 fn is_void_element(name: &str) -> bool {
-    matches!(
-        name,
-        "area"
-            | "base"
-            | "br"
-            | "col"
-            | "embed"
-            | "hr"
-            | "img"
-            | "input"
-            | "link"
-            | "meta"
-            | "param"
-            | "source"
-            | "track"
-            | "wbr"
-    )
+    VOID_ELEMENTS.contains(&name)
+}
+
+/// Components are named in PascalCase (`<Spinner />`) or path-qualified (`<icons::Spinner />`)
+fn is_component(name: &NodeName) -> bool {
+    let NodeName::Path(path) = name else {
+        return false;
+    };
+
+    let path = &path.path;
+    path.leading_colon.is_some()
+        || path.segments.len() > 1
+        || path
+            .segments
+            .first()
+            .is_some_and(|segment| segment.ident.to_string().starts_with(char::is_uppercase))
 }
 
 fn is_self_closing(
@@ -183,7 +411,8 @@ mod tests {
     use crate::{
         formatter::{ClosingTagStyle, FormatterSettings},
         test_helpers::{element, format_element_from_string, format_with},
-        AttributeValueBraceStyle,
+        AttributeGroupOrder, AttributeValueBraceStyle, AttributeWrap, ChildrenLayout,
+        WhitespaceSensitivity,
     };
 
     macro_rules! format_element {
@@ -285,6 +514,36 @@ mod tests {
         "###);
     }
 
+    #[test]
+    fn trailing_comments() {
+        let formatted = format_element_from_string!(indoc! {r#"
+        <div class="sidebar" // sidebar
+            id="sidebar">
+            <input type="text" // legacy API
+            />
+            <span>"hello"</span>    //no space
+            <p>
+                "A paragraph with some text" <b>"bold"</b> // a long comment that counts
+            </p>
+        </div>
+        "#});
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div
+            class="sidebar" // sidebar
+            id="sidebar"
+        >
+            <input type="text" // legacy API
+            />
+            <span>"hello"</span> // no space
+            <p>
+                "A paragraph with some text"
+                <b>"bold"</b> // a long comment that counts
+            </p>
+        </div>
+        "#);
+    }
+
     #[test]
     fn child_element() {
         let formatted = format_element! { <div><span>"hello"</span></div> };
@@ -467,8 +726,7 @@ mod tests {
         insta::assert_snapshot!(formatted, @r#"
         <div>
             <div class="foo">
-                <i class="bi-google"></i>
-                "Sign in with google"
+                <i class="bi-google"></i> "Sign in with google"
             </div>
         </div>
         "#);
@@ -624,4 +882,414 @@ mod tests {
         insta::assert_snapshot!(unless_lit, @"<div foo=12 {..} a={|| {}} {..} />");
         insta::assert_snapshot!(always, @"<div foo={bar} {..} a={12} {..} />");
     }
+
+    fn leptos_attr_order() -> Vec<Vec<String>> {
+        [
+            "node_ref", "id class", "*", "prop:*", "attr:*", "class:*", "style:*", "on:*", "use:*",
+            "let:*",
+        ]
+        .iter()
+        .map(|group| group.split(' ').map(String::from).collect())
+        .collect()
+    }
+
+    #[test]
+    fn attr_order_groups() {
+        let settings = FormatterSettings {
+            attr_order: leptos_attr_order(),
+            ..Default::default()
+        };
+        let formatted = format_element_with! { settings, <input on:input=handler type="text" class:active=a class="b" node_ref=input_ref value=v /> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <input
+            node_ref=input_ref
+            class="b"
+            type="text"
+            value=v
+            class:active=a
+            on:input=handler
+        />
+        "#);
+    }
+
+    #[test]
+    fn attr_order_alphabetical_within_group() {
+        let settings = FormatterSettings {
+            attr_order: leptos_attr_order(),
+            attr_group_order: AttributeGroupOrder::Alphabetical,
+            ..Default::default()
+        };
+        let formatted = format_element_with! { settings, <div on:click=a title="t" class="c" id="i" aria-label="l" on:blur=b /> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div
+            class="c"
+            id="i"
+            aria-label="l"
+            title="t"
+            on:blur=b
+            on:click=a
+        />
+        "#);
+    }
+
+    #[test]
+    fn attr_order_keeps_spread_position() {
+        let settings = FormatterSettings {
+            attr_order: leptos_attr_order(),
+            ..Default::default()
+        };
+        let formatted =
+            format_element_with! { settings, <div on:click=a foo={bar} {..} b=1 class="c" /> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div
+            foo=bar
+            on:click={a}
+            {..}
+            class="c"
+            b=1
+        />
+        "#);
+
+        // The attributes before the spread must not be parsed as a struct literal
+        let nodes = rstml::parse2(formatted.parse().unwrap()).unwrap();
+        let rstml::node::Node::Element(element) = &nodes[0] else {
+            panic!("expected an element");
+        };
+        assert_eq!(element.attributes().len(), 5);
+    }
+
+    #[test]
+    fn attr_wrap_always() {
+        let settings = FormatterSettings {
+            attr_wrap: AttributeWrap::Always,
+            ..Default::default()
+        };
+        let single = format_element_with! { settings.clone(), <div class="a"></div> };
+        let multi = format_element_with! { settings, <div class="a" id="b"></div> };
+
+        insta::assert_snapshot!(single, @r#"<div class="a"></div>"#);
+        insta::assert_snapshot!(multi, @r#"
+        <div
+            class="a"
+            id="b"
+        ></div>
+        "#);
+    }
+
+    #[test]
+    fn attr_wrap_never() {
+        let settings = FormatterSettings {
+            attr_wrap: AttributeWrap::Never,
+            ..Default::default()
+        };
+        let formatted = format_element_with! { settings, <div key=a::very::deeply::nested::module::generate_key() width=100>"hello"</div> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div key=a::very::deeply::nested::module::generate_key() width=100>
+            "hello"
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn max_attrs_per_line() {
+        let settings = FormatterSettings {
+            max_attrs_per_line: Some(2),
+            ..Default::default()
+        };
+        let two = format_element_with! { settings.clone(), <input a=1 b=2 /> };
+        let three = format_element_with! { settings, <input a=1 b=2 c=3 /> };
+
+        insta::assert_snapshot!(two, @"<input a=1 b=2 />");
+        insta::assert_snapshot!(three, @r#"
+        <input
+            a=1
+            b=2
+            c=3
+        />
+        "#);
+    }
+
+    #[test]
+    fn attr_wrap_multiline_value() {
+        let formatted = format_element! { <div a=1 on:click=move |_| { foo(); bar() }></div> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div
+            a=1
+            on:click=move |_| {
+                foo();
+                bar()
+            }
+        ></div>
+        "#);
+    }
+
+    #[test]
+    fn bracket_same_line() {
+        let settings = FormatterSettings {
+            bracket_same_line: true,
+            ..Default::default()
+        };
+        let formatted = format_element_with! { settings.clone(), <div key=a::very::deeply::nested::module::generate_key() width=100>"hello"</div> };
+        let self_closing = format_element_with! { settings, <div key=a::very::deeply::nested::module::generate_key() width=100 /> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div
+            key=a::very::deeply::nested::module::generate_key()
+            width=100>
+            "hello"
+        </div>
+        "#);
+        insta::assert_snapshot!(self_closing, @r#"
+        <div
+            key=a::very::deeply::nested::module::generate_key()
+            width=100
+        />
+        "#);
+    }
+
+    #[test]
+    fn self_closing_bracket_same_line() {
+        let settings = FormatterSettings {
+            self_closing_bracket_same_line: true,
+            ..Default::default()
+        };
+        let formatted = format_element_with! { settings.clone(), <div key=a::very::deeply::nested::module::generate_key() width=100>"hello"</div> };
+        let self_closing = format_element_with! { settings, <div key=a::very::deeply::nested::module::generate_key() width=100 /> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div
+            key=a::very::deeply::nested::module::generate_key()
+            width=100
+        >
+            "hello"
+        </div>
+        "#);
+        insta::assert_snapshot!(self_closing, @r#"
+        <div
+            key=a::very::deeply::nested::module::generate_key()
+            width=100 />
+        "#);
+    }
+
+    #[test]
+    fn component_closing_tag_style() {
+        let settings = FormatterSettings {
+            closing_tag_style: ClosingTagStyle::NonSelfClosing,
+            component_closing_tag_style: Some(ClosingTagStyle::SelfClosing),
+            ..Default::default()
+        };
+        let element = format_element_with! { settings.clone(), <div /> };
+        let component = format_element_with! { settings.clone(), <Spinner></Spinner> };
+        let path_component = format_element_with! { settings, <icons::spinner></icons::spinner> };
+
+        insta::assert_snapshot!(element, @"<div></div>");
+        insta::assert_snapshot!(component, @"<Spinner />");
+        insta::assert_snapshot!(path_component, @"<icons::spinner />");
+    }
+
+    #[test]
+    fn children_layout_fill() {
+        let settings = FormatterSettings {
+            children_layout: ChildrenLayout::Fill,
+            ..Default::default()
+        };
+        let formatted =
+            format_element_with! { settings, <div><span>"a"</span><span>"b"</span></div> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <span>"a"</span><span>"b"</span>
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn children_layout_one_per_line() {
+        let settings = FormatterSettings {
+            children_layout: ChildrenLayout::OnePerLine,
+            ..Default::default()
+        };
+        let formatted = format_element_with! { settings, <div>"a" {b} "c"</div> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            "a"
+            {b}
+            "c"
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn max_children_per_line() {
+        let settings = FormatterSettings {
+            max_children_per_line: Some(2),
+            ..Default::default()
+        };
+        let two = format_element_with! { settings.clone(), <div>"a" {b}</div> };
+        let three = format_element_with! { settings, <div>"a" {b} "c"</div> };
+
+        insta::assert_snapshot!(two, @r#"<div>"a" {b}</div>"#);
+        insta::assert_snapshot!(three, @r#"
+        <div>
+            "a"
+            {b}
+            "c"
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn inline_elements_are_filled() {
+        let formatted = format_element! { <p>"Read the "<a href="/docs">"docs"</a>" or "<strong>"ask"</strong>" on discord"</p> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <p>
+            "Read the " <a href="/docs">"docs"</a>" or "
+            <strong>"ask"</strong>" on discord"
+        </p>
+        "#);
+    }
+
+    #[test]
+    fn block_elements_break_text() {
+        let formatted = format_element! { <div>"a" <div>"b"</div></div> };
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            "a"
+            <div>"b"</div>
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn custom_inline_elements() {
+        let settings = FormatterSettings {
+            inline_elements: vec!["A".to_string()],
+            ..Default::default()
+        };
+        let formatted = format_element_with! { settings, <p><A href="/">"home"</A>" page"</p> };
+
+        insta::assert_snapshot!(formatted, @r#"<p><A href="/">"home"</A>" page"</p>"#);
+    }
+
+    #[test]
+    fn whitespace_sensitivity_strict() {
+        let strict = |source| {
+            format_element_from_string(
+                FormatterSettings {
+                    max_width: 40,
+                    whitespace_sensitivity: WhitespaceSensitivity::Strict,
+                    ..Default::default()
+                },
+                source,
+            )
+        };
+
+        let space_before_text = r#"<p><b>"bold"</b> and unquoted text</p>"#;
+        let glued = r#"<p>"A sentence that is too long to fit "<b>"on a single line"</b>"!"</p>"#;
+
+        insta::assert_snapshot!(format_element_from_string!(space_before_text), @r#"<p><b>"bold"</b>and unquoted text</p>"#);
+        insta::assert_snapshot!(strict(space_before_text), @r#"<p><b>"bold"</b> and unquoted text</p>"#);
+        insta::assert_snapshot!(format_element_from_string!(glued), @r#"
+        <p>
+            "A sentence that is too long to fit "
+            <b>"on a single line"</b>"!"
+        </p>
+        "#);
+        insta::assert_snapshot!(strict(glued), @r#"
+        <p>
+            "A sentence that is too long to fit "<b>
+                "on a single line"
+            </b>"!"
+        </p>
+        "#);
+    }
+
+    #[test]
+    fn preserve_elements() {
+        let formatted = format_element_from_string!(indoc! {r#"
+            <div><pre>
+              "x   y"
+                <b>"z"</b>  // comment
+            </pre>
+            <textarea>"  a"
+            </textarea></div>
+        "#});
+
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <pre>
+          "x   y"
+            <b>"z"</b>  // comment
+            </pre>
+            <textarea>"  a"
+            </textarea>
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn preserve_elements_moved_to_other_level() {
+        let source = indoc! {r#"
+            <section>
+                        <div>
+                <pre>
+              "x   y"
+                </pre>
+                        </div>
+            </section>
+        "#};
+        let formatted = format_element_from_string!(source);
+        insta::assert_snapshot!(formatted, @r#"
+        <section>
+            <div>
+                <pre>
+          "x   y"
+                </pre>
+            </div>
+        </section>
+        "#);
+
+        // Formatting again does not move the closing tag
+        assert_eq!(format_element_from_string!(&formatted), formatted);
+    }
+
+    #[test]
+    fn preserve_elements_multi_line_width() {
+        // Only the width of the last line of the preserved content counts towards the line it ends on
+        let settings = FormatterSettings {
+            children_layout: ChildrenLayout::Fill,
+            ..Default::default()
+        };
+        let formatted = format_element_from_string(
+            settings,
+            indoc! {r#"
+                <p>"Some text" <textarea>"a first line that is long enough to not fit on the line of the paragraph"
+                "b"</textarea> "more text"</p>
+            "#},
+        );
+        insta::assert_snapshot!(formatted, @r#"
+        <p>
+            "Some text" <textarea>"a first line that is long enough to not fit on the line of the paragraph"
+        "b"</textarea> "more text"
+        </p>
+        "#);
+    }
+
+    #[test]
+    fn wide_characters_fit_by_display_width() {
+        // 45 wide characters take up 90 columns (and 135 bytes)
+        let formatted = format_element_from_string(
+            Default::default(),
+            r#"<p>"日本語のテキストは表示幅で測られるので、この段落は最大幅に収まります。あいうえおかきくけこ"</p>"#,
+        );
+        insta::assert_snapshot!(formatted, @r#"<p>"日本語のテキストは表示幅で測られるので、この段落は最大幅に収まります。あいうえおかきくけこ"</p>"#);
+    }
 }
@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use quote::ToTokens;
+use syn::{spanned::Spanned, visit::Visit, Block, Expr, ExprBlock, ExprLit, LitStr};
 
-use syn::{spanned::Spanned, Block, Expr, ExprBlock, ExprLit, LitStr};
-
-use crate::{formatter::Formatter, get_text_beween_spans, view_macro::ViewMacroFormatter};
+use crate::{
+    collect::StatementMacros, formatter::Formatter, get_text_beween_spans,
+    view_macro::ViewMacroFormatter,
+};
 
 use super::ExpressionFormatter;
 
@@ -35,11 +37,34 @@ impl Formatter<'_> {
         }
     }
 
-    pub fn source_code<T: Spanned>(&mut self, span: &T) {
-        let span = span.span();
-        let source = self.source.unwrap();
-        let code_fragment = get_text_beween_spans(source, span.start(), span.end()).to_string();
-        self.string(&code_fragment, span.start().column)
+    /// Prints multi-line text with the continuation lines re-indented relative to the current indentation,
+    /// the indentation of the continuation lines relative to each other is kept
+    pub fn reindented_string(&mut self, string: &str) {
+        let common_indent = string
+            .lines()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+            .min()
+            .unwrap_or_default();
+
+        self.string(string, common_indent)
+    }
+
+    /// Prints the original source text of a node, falling back to its token representation when no source is available.
+    pub fn source_code<T: ToTokens>(&mut self, node: &T) {
+        let code_fragment = self.source_text(node);
+        self.string(&code_fragment, node.span().start().column)
+    }
+
+    pub(crate) fn source_text<T: ToTokens>(&self, node: &T) -> String {
+        let span = node.span();
+        match self.source {
+            Some(source) => get_text_beween_spans(source, span.start(), span.end()).to_string(),
+            None => span
+                .source_text()
+                .unwrap_or_else(|| node.to_token_stream().to_string()),
+        }
     }
 
     pub fn literal_str(&mut self, lit_str: &LitStr) {
@@ -108,8 +133,6 @@ impl Formatter<'_> {
     }
 
     fn expr(&mut self, expr: &syn::Expr, formatter: Option<ExpressionFormatter>) {
-        let span = expr.span();
-        self.flush_comments(span.start().line - 1, false);
         if let syn::Expr::Lit(ExprLit {
             lit: syn::Lit::Str(lit_str),
             ..
@@ -123,28 +146,18 @@ impl Formatter<'_> {
             return;
         }
 
-        let start_line = span.start().line - 1;
-        let end_line = span.end().line - 1;
-
-        let cmt_or_wp_lines: Vec<usize> = self
-            .whitespace_and_comments
-            .iter()
-            .filter(|(line, _comment)| **line >= start_line && **line < end_line)
-            .map(|(line, _)| *line)
-            .collect();
-
-        let comments_or_whitespace = cmt_or_wp_lines
-            .into_iter()
-            .map(|line| (line, self.whitespace_and_comments.remove(&line).unwrap()))
-            .collect::<HashMap<_, _>>();
+        // The expression may be printed within braces, where it is in statement position
+        let mut statement_macros = StatementMacros::default();
+        statement_macros.record_expr(expr);
+        statement_macros.visit_expr(expr);
 
         leptosfmt_prettyplease::unparse_fn(
             self.printer,
             Some(&mut ViewMacroFormatter::new(
                 self.settings,
                 self.source,
-                &mut self.line_offset,
-                comments_or_whitespace,
+                &mut self.trivia,
+                statement_macros,
             )),
             |p| p.expr_without_fixup(expr),
         );
@@ -157,20 +170,6 @@ mod tests {
     use crate::formatter::*;
     use crate::test_helpers::format_element_from_string;
 
-    macro_rules! format_element {
-        ($($tt:tt)*) => {{
-            let settings = FormatterSettings {
-                max_width: 40,
-                ..Default::default()
-            };
-
-            let element = element! { $($tt)* };
-            format_with(settings,|formatter| {
-                formatter.node(&Node::Element(element));
-            })
-        }};
-    }
-
     macro_rules! format_element_from_string {
         ($($tt:tt)*) => {{
             let settings = FormatterSettings {
@@ -256,6 +255,86 @@ mod tests {
         "###);
     }
 
+    #[test]
+    fn wrap_unquoted_text() {
+        let settings = FormatterSettings {
+            max_width: 80,
+            wrap_text: true,
+            ..Default::default()
+        };
+
+        let source = r#"<div><p>Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor incididunt ut labore
+                et dolore magna aliqua.   Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.</p>
+            <p>Short text</p></div>"#;
+
+        let formatted = format_element_from_string(settings, source);
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <p>
+                Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod
+                tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim
+                veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea
+                commodo consequat.
+            </p>
+            <p>Short text</p>
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn reindent_unquoted_text() {
+        let settings = FormatterSettings {
+            max_width: 40,
+            reindent_unquoted_text: true,
+            ..Default::default()
+        };
+
+        let source = r#"<div><p>Lorem ipsum dolor sit amet,
+                        consectetur adipiscing elit,
+                            sed do eiusmod tempor.</p>
+            <p>"Quoted text
+                        keeps its indentation"</p></div>"#;
+
+        let formatted = format_element_from_string(settings, source);
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <p>
+                Lorem ipsum dolor sit amet,
+                consectetur adipiscing elit,
+                    sed do eiusmod tempor.
+            </p>
+            <p>
+                "Quoted text
+                         keeps its indentation"
+            </p>
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn reindent_quoted_text() {
+        let settings = FormatterSettings {
+            max_width: 40,
+            reindent_quoted_text: true,
+            ..Default::default()
+        };
+
+        let source = r#"<div><p>"Quoted text
+                        is re-indented
+                            as well"</p></div>"#;
+
+        let formatted = format_element_from_string(settings, source);
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <p>
+                "Quoted text
+                is re-indented
+                    as well"
+            </p>
+        </div>
+        "#);
+    }
+
     #[test]
     fn raw_string_as_child() {
         let formatted = format_element_from_string!(r##"<p>r#"some" string"#</p>"##);
@@ -1,11 +1,20 @@
 use rstml::{node::NodeFragment, Infallible};
 
+use syn::spanned::Spanned;
+
 use crate::formatter::Formatter;
 
 impl Formatter<'_> {
     pub fn fragment(&mut self, fragment: &NodeFragment<Infallible>) {
         self.printer.word("<>");
-        self.children(&fragment.children, 0);
+        self.trailing_comment(fragment.tag_open.span().end());
+
+        // Comments before the closing tag keep the closing tag
+        let dangling = match &fragment.tag_close {
+            Some(tag_close) => self.closing_tag_trivia(tag_close.span()),
+            None => Vec::new(),
+        };
+        self.children(&fragment.children, 0, dangling);
         self.printer.word("</>");
     }
 }
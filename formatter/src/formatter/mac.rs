@@ -1,13 +1,14 @@
 use crop::Rope;
 use leptosfmt_pretty_printer::Printer;
-use proc_macro2::{token_stream, Span, TokenStream, TokenTree};
+use proc_macro2::{token_stream, LineColumn, Span, TokenStream, TokenTree};
 use quote::ToTokens;
 use rstml::node::Node;
-use syn::{spanned::Spanned, Macro};
+use syn::{spanned::Spanned, Macro, MacroDelimiter};
 
 use crate::view_macro::get_macro_full_path;
 
-use super::{Formatter, FormatterSettings};
+use super::{Formatter, FormatterSettings, MacroDelimiterStyle};
+use crate::{trivia::Trivia, ParserSettings};
 
 pub struct ViewMacro<'a> {
     pub parent_indent: ParentIndent,
@@ -17,16 +18,38 @@ pub struct ViewMacro<'a> {
     pub span: Span,
     pub mac: &'a Macro,
     pub comma: Option<TokenTree>,
+    /// Whether the delimiter has to be kept as written, see `StatementMacros`
+    pub keep_delimiter: bool,
 }
 
 #[derive(Default, Debug)]
+#[non_exhaustive]
 pub struct ParentIndent {
     pub tabs: usize,
     pub spaces: usize,
+    /// Width of the code before the macro on its first line, after the indentation (e.g. `let body = Some(`)
+    pub prefix_width: usize,
+    /// Width of the code after the macro on its last line (e.g. `).into_any();`)
+    pub suffix_width: usize,
+}
+
+impl ParentIndent {
+    /// Indentation of a macro that has no code before or after it on its lines
+    pub fn new(tabs: usize, spaces: usize) -> Self {
+        Self {
+            tabs,
+            spaces,
+            ..Default::default()
+        }
+    }
 }
 
 impl<'a> ViewMacro<'a> {
-    pub fn try_parse(parent_indent: ParentIndent, mac: &'a Macro) -> Option<Self> {
+    pub fn try_parse(
+        parent_indent: ParentIndent,
+        mac: &'a Macro,
+        parser_settings: &ParserSettings,
+    ) -> Option<Self> {
         let mut tokens = mac.tokens.clone().into_iter();
         let (cx, comma) = (tokens.next(), tokens.next());
 
@@ -54,7 +77,7 @@ impl<'a> ViewMacro<'a> {
         let (tokens, global_class) = extract_global_class(tokens)?;
 
         let span = mac.span();
-        let nodes = rstml::parse2(tokens).ok()?;
+        let nodes = parser_settings.parse(tokens)?;
 
         Some(Self {
             parent_indent,
@@ -64,6 +87,7 @@ impl<'a> ViewMacro<'a> {
             mac,
             cx,
             comma,
+            keep_delimiter: false,
         })
     }
 
@@ -82,55 +106,117 @@ impl Formatter<'_> {
             ..
         } = view_mac;
 
-        self.printer
-            .cbox((parent_indent.tabs * self.settings.tab_spaces + parent_indent.spaces) as isize);
-
-        self.flush_comments(
-            cx.as_ref()
-                .map(|cx| cx.span())
-                .unwrap_or_else(|| view_mac.mac.delimiter.span().open())
-                .start()
-                .line
-                - 1,
-            false,
-        );
+        let indent = parent_indent.tabs * self.settings.tab_spaces + parent_indent.spaces;
+        self.printer.cbox(indent as isize);
 
-        let macro_word = format!("{}! {{", get_macro_full_path(view_mac.mac));
-        self.printer.word(macro_word);
+        // Comments and blank lines before a view macro nested in rust code
+        self.leading_trivia(view_mac.mac.path.span().start(), false);
 
-        if let Some(cx) = cx {
-            self.printer.word(" ");
-            self.printer.word(cx.to_string());
-            self.printer.word(",");
+        let delimiter = view_mac.mac.delimiter.span();
+
+        // Comments around the scope and global class cannot be kept in place, they are printed above the macro
+        if let Some(prefix_end) = global_class.as_ref().or(cx.as_ref()) {
+            let comments =
+                self.trivia
+                    .take_within(delimiter.open().end(), prefix_end.span().end(), &[]);
+            for comment in comments {
+                self.source_comment(&comment, true);
+            }
         }
 
+        let braces = match view_mac.mac.delimiter {
+            MacroDelimiter::Brace(_) => true,
+            _ => {
+                self.settings.macro_delimiter == MacroDelimiterStyle::Brace
+                    && !view_mac.keep_delimiter
+            }
+        };
+        let (open, close) = match view_mac.mac.delimiter {
+            _ if braces => (" {", "}"),
+            MacroDelimiter::Paren(_) => ("(", ")"),
+            MacroDelimiter::Bracket(_) => ("[", "]"),
+            MacroDelimiter::Brace(_) => unreachable!("braces are always kept"),
+        };
+
+        let leading_colon = if view_mac.mac.path.leading_colon.is_some() {
+            "::"
+        } else {
+            ""
+        };
+        self.printer.word(format!(
+            "{leading_colon}{}!{open}",
+            get_macro_full_path(view_mac.mac)
+        ));
+        self.trailing_comment(delimiter.open().end());
+
+        let mut prefix = Vec::new();
+        if let Some(cx) = cx {
+            prefix.push(format!("{cx},"));
+        }
         if let Some(global_class) = global_class {
-            self.printer.word(" class=");
-            self.printer.word(global_class.to_string());
-            self.printer.word(",");
+            prefix.push(format!("class={global_class},"));
+        }
+
+        for (idx, word) in prefix.iter().enumerate() {
+            if braces || idx > 0 {
+                self.printer.word(" ");
+            }
+            self.printer.word(word.clone());
         }
 
-        self.trim_whitespace(nodes.first().span().start().line - 1);
-        self.view_macro_nodes(nodes, view_mac.mac.span());
-        self.printer.word("}");
+        // `view! { <div /> }` has spaces within the braces, `view!(<div />)` does not
+        self.view_macro_nodes(
+            nodes,
+            delimiter.close().start(),
+            braces || !prefix.is_empty(),
+            braces,
+        );
+        self.printer.word(close);
+        self.printer.reserve_end(parent_indent.suffix_width);
         self.printer.end();
     }
 
-    fn view_macro_nodes(&mut self, nodes: &[Node], mac_span: Span) {
+    fn view_macro_nodes(
+        &mut self,
+        nodes: &[Node],
+        close_delimiter: LineColumn,
+        space_before: bool,
+        space_after: bool,
+    ) {
         self.printer.cbox_indent();
-        self.printer.space();
+        if space_before {
+            self.printer.space();
+        } else {
+            self.printer.zerobreak();
+        }
 
+        // Blank lines at the start of the macro are removed
+        if let Some(first) = nodes.first() {
+            self.leading_trivia(first.span().start(), true);
+        }
+
+        let mut previous = None;
         let mut iter = nodes.iter().peekable();
         while let Some(node) = iter.next() {
-            self.node(node);
+            self.child_node(node, previous.replace(node), iter.peek().copied());
 
             if iter.peek().is_some() {
                 self.printer.hardbreak();
+                self.separate_siblings();
             }
         }
 
-        self.printer.space();
-        self.flush_comments(mac_span.end().line - 1, true);
+        let dangling = self.trivia.take_leading(close_delimiter);
+        if dangling.iter().any(Trivia::is_comment) {
+            self.printer.hardbreak();
+            self.dangling_trivia(dangling);
+        }
+
+        if space_after {
+            self.printer.space();
+        } else {
+            self.printer.zerobreak();
+        }
         self.printer.end_dedent();
     }
 }
@@ -177,16 +263,19 @@ pub fn format_macro(
     let mut printer = Printer::new(settings.to_printer_settings(source));
     let mut formatter = match source {
         Some(source) => {
-            let whitespace = crate::collect_comments::extract_whitespace_and_comments(
-                source,
-                mac.mac.to_token_stream(),
-            );
-
-            Formatter::with_source(settings, &mut printer, source, whitespace)
+            Formatter::with_source(settings, &mut printer, source, mac.mac.to_token_stream())
         }
         None => Formatter::new(settings, &mut printer),
     };
 
+    // The macro starts after the indentation and the code before it
+    let parent_indent = &mac.parent_indent;
+    formatter.printer.reserve_start(
+        parent_indent.tabs * settings.tab_spaces
+            + parent_indent.spaces
+            + parent_indent.prefix_width,
+    );
+
     formatter.view_macro(mac);
     printer.eof()
 }
@@ -201,7 +290,7 @@ mod tests {
     macro_rules! view_macro {
         ($($tt:tt)*) => {{
             let mac: Macro = syn::parse2(quote! { $($tt)* }).unwrap();
-            format_macro(&ViewMacro::try_parse(Default::default(), &mac).unwrap(), &Default::default(), None)
+            format_macro(&ViewMacro::try_parse(Default::default(), &mac, &Default::default()).unwrap(), &Default::default(), None)
         }}
     }
 
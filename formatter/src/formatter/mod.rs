@@ -12,14 +12,19 @@ mod fragment;
 mod mac;
 mod node;
 mod tailwind;
+mod trivia;
 
+pub(crate) use element::{INLINE_ELEMENTS, VOID_ELEMENTS};
 pub use mac::format_macro;
 pub use mac::{ParentIndent, ViewMacro};
 
+use proc_macro2::TokenStream;
 use serde::Deserialize;
 use serde::Serialize;
 use syn::{Generics, Pat};
 
+use crate::{trivia::SourceTrivia, ParserSettings};
+
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub enum ClosingTagStyle {
     /// Preserve the original closing tag style (self-closing or a separate closing tag)
@@ -38,6 +43,69 @@ pub enum AttributeValueBraceStyle {
     Preserve,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ChildrenBraceStyle {
+    /// Keep the braces around child blocks: `{"text"}` stays `{"text"}`
+    Always,
+    /// Remove the braces around string literals, which turns them into text: `{"text"}` formats to `"text"`
+    WhenRequired,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum AttributeGroupOrder {
+    /// Keep attributes within a group in the order they were written
+    AsWritten,
+    /// Sort attributes within a group alphabetically by name
+    Alphabetical,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum AttributeWrap {
+    /// Put every attribute on its own line when they do not fit on one line (or exceed `max_attrs_per_line`)
+    Auto,
+    /// Put every attribute on its own line when an element has more than one attribute
+    Always,
+    /// Keep all attributes on the same line as the tag name
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum ChildrenLayout {
+    /// Fill textual children onto as few lines as possible, put other children on their own line
+    Auto,
+    /// Fill children onto as few lines as possible
+    Fill,
+    /// Put every child on its own line
+    OnePerLine,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum WhitespaceSensitivity {
+    /// Whitespace between nodes is added or removed freely
+    Ignore,
+    /// Whitespace between inline nodes (text, blocks and `inline_elements`) is never added or removed
+    Strict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum TextStyle {
+    /// Keep text quoted or unquoted as written
+    Preserve,
+    /// Quote unquoted text: `<p>Hello</p>` formats to `<p>"Hello"</p>`
+    Quoted,
+    /// Unquote text that stays the same without quotes: `<p>"Hello"</p>` formats to `<p>Hello</p>`
+    Unquoted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum MacroDelimiterStyle {
+    /// Keep the delimiter of the macro as written: `view! { }`, `view!( )` or `view![ ]`
+    Preserve,
+    /// Use braces for every macro: `view!(<div />)` formats to `view! { <div /> }`,
+    /// unless the macro starts a larger statement (e.g. `view!(<div />)[0];` or `view!(<div />) as View;`), where braces would end the statement
+    Brace,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub enum IndentationStyle {
     Auto,
@@ -84,14 +152,81 @@ pub struct FormatterSettings {
     /// Determines placement of braces around single expression attribute values
     pub attr_value_brace_style: AttributeValueBraceStyle,
 
+    /// Determines whether braces around string literal children are removed
+    pub children_brace_style: ChildrenBraceStyle,
+
+    /// Put spaces inside the braces of child blocks with a single expression: `{ x }` instead of `{x}`
+    pub children_brace_spacing: bool,
+
     /// Preferred style for closing tags (self-closing or not) when a non-void element has no children
     pub closing_tag_style: ClosingTagStyle,
 
+    /// Overrides `closing_tag_style` for components (PascalCase or path-qualified names, e.g. `<Spinner>`)
+    pub component_closing_tag_style: Option<ClosingTagStyle>,
+
     /// Determines macros to be formatted. Default: leptos::view, view
     pub macro_names: Vec<String>,
 
+    /// Determines whether the delimiter of view macros is kept as written or normalised to braces
+    pub macro_delimiter: MacroDelimiterStyle,
+
     /// Determines whether to format attribute values with a specific formatter (e.g. tailwind)
     pub attr_values: HashMap<String, ExpressionFormatter>,
+
+    /// Groups of attribute name patterns (e.g. `id`, `on:*` or `*`), attributes are printed group by group.
+    /// Empty to keep the original attribute order
+    pub attr_order: Vec<Vec<String>>,
+
+    /// Determines the order of attributes within the same `attr_order` group
+    pub attr_group_order: AttributeGroupOrder,
+
+    /// Determines when every attribute is put on its own line
+    pub attr_wrap: AttributeWrap,
+
+    /// Maximum number of attributes on a single line, more attributes put every attribute on its own line
+    pub max_attrs_per_line: Option<usize>,
+
+    /// Put the `>` of a broken opening tag on the line of the last attribute, instead of on its own line
+    pub bracket_same_line: bool,
+
+    /// Put the `/>` of a broken self-closing tag on the line of the last attribute, instead of on its own line
+    pub self_closing_bracket_same_line: bool,
+
+    /// Determines whether children are filled onto as few lines as possible, or put on a line of their own
+    pub children_layout: ChildrenLayout,
+
+    /// Maximum number of children on a single line, more children put every child on its own line
+    pub max_children_per_line: Option<usize>,
+
+    /// Elements that flow with text (e.g. `span` or `a`), text mixed with these elements is filled onto as few lines as possible
+    pub inline_elements: Vec<String>,
+
+    /// Determines whether whitespace between inline nodes may be added or removed
+    pub whitespace_sensitivity: WhitespaceSensitivity,
+
+    /// Determines whether text children are quoted (`"Hello"`) or unquoted (`Hello`)
+    pub text_style: TextStyle,
+
+    /// Re-wrap the words of unquoted text to fit within `max_width`
+    pub wrap_text: bool,
+
+    /// Re-indent the continuation lines of multi-line unquoted text relative to the position of the text
+    pub reindent_unquoted_text: bool,
+
+    /// Re-indent the continuation lines of multi-line quoted text as well, which changes the whitespace within the string
+    pub reindent_quoted_text: bool,
+
+    /// Elements of which the children are printed exactly as written (e.g. `pre` or `style`)
+    pub preserve_elements: Vec<String>,
+
+    /// Maximum number of consecutive blank lines kept between nodes and attributes
+    pub blank_lines_upper_bound: usize,
+
+    /// Minimum number of blank lines between nodes that are put on a line of their own
+    pub blank_lines_lower_bound: usize,
+
+    /// Determines how view macros are parsed, should match the parser of the formatted macro
+    pub parser: ParserSettings,
 }
 
 impl Default for FormatterSettings {
@@ -100,11 +235,39 @@ impl Default for FormatterSettings {
             max_width: 100,
             tab_spaces: 4,
             attr_value_brace_style: AttributeValueBraceStyle::WhenRequired,
+            children_brace_style: ChildrenBraceStyle::Always,
+            children_brace_spacing: false,
             indentation_style: IndentationStyle::Auto,
             newline_style: NewlineStyle::Auto,
             closing_tag_style: ClosingTagStyle::Preserve,
+            component_closing_tag_style: None,
             macro_names: vec!["leptos::view".to_string(), "view".to_string()],
+            macro_delimiter: MacroDelimiterStyle::Preserve,
             attr_values: HashMap::new(),
+            attr_order: Vec::new(),
+            attr_group_order: AttributeGroupOrder::AsWritten,
+            attr_wrap: AttributeWrap::Auto,
+            max_attrs_per_line: None,
+            bracket_same_line: false,
+            self_closing_bracket_same_line: false,
+            children_layout: ChildrenLayout::Auto,
+            max_children_per_line: None,
+            inline_elements: INLINE_ELEMENTS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            whitespace_sensitivity: WhitespaceSensitivity::Ignore,
+            text_style: TextStyle::Preserve,
+            wrap_text: false,
+            reindent_unquoted_text: false,
+            reindent_quoted_text: false,
+            preserve_elements: ["pre", "textarea", "script", "style"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            blank_lines_upper_bound: 1,
+            blank_lines_lower_bound: 0,
+            parser: ParserSettings::default(),
         }
     }
 }
@@ -150,8 +313,8 @@ pub struct Formatter<'a> {
     pub printer: &'a mut leptosfmt_pretty_printer::Printer,
     pub settings: &'a FormatterSettings,
     pub(crate) source: Option<&'a Rope>,
-    pub(crate) whitespace_and_comments: HashMap<usize, Option<String>>,
-    pub(crate) line_offset: Option<usize>,
+    pub(crate) trivia: SourceTrivia,
+    pub(crate) min_blank_lines: usize,
 }
 
 impl<'a> Formatter<'a> {
@@ -160,79 +323,24 @@ impl<'a> Formatter<'a> {
             printer,
             settings,
             source: None,
-            whitespace_and_comments: HashMap::new(),
-            line_offset: None,
+            trivia: SourceTrivia::default(),
+            min_blank_lines: 0,
         }
     }
+    /// Creates a formatter that keeps the comments and blank lines found in `source` between the given tokens
     pub fn with_source(
         settings: &'a FormatterSettings,
         printer: &'a mut Printer,
         source: &'a Rope,
-        comments: HashMap<usize, Option<String>>,
+        tokens: TokenStream,
     ) -> Self {
         Self {
             printer,
             settings,
             source: Some(source),
-            whitespace_and_comments: comments,
-            line_offset: None,
-        }
-    }
-
-    pub fn trim_whitespace(&mut self, line_index: usize) {
-        // keep removing whitespace until we reach the current line or a comment
-        let last = self.line_offset.unwrap_or(0);
-
-        for line in last..=line_index {
-            if let Some(entry) = self.whitespace_and_comments.get(&line) {
-                if entry.is_none() {
-                    self.whitespace_and_comments.remove(&line);
-                } else {
-                    return;
-                }
-            }
-        }
-    }
-
-    pub fn flush_comments(&mut self, line_index: usize, skip_trailing_whitespace: bool) {
-        let last = self.line_offset.unwrap_or(0);
-
-        let comments_or_empty_lines: Vec<_> = (last..=line_index)
-            .filter_map(|l| self.whitespace_and_comments.remove(&l))
-            .collect();
-
-        // If we need to skip trailing whitespace, calculate how many elements we need to take,
-        // until no comments are left in the vector
-        let take_n = if skip_trailing_whitespace {
-            comments_or_empty_lines
-                .iter()
-                .rev()
-                .position(Option::is_some)
-                .map(|i| comments_or_empty_lines.len() - i)
-        } else {
-            None
-        }
-        .unwrap_or(comments_or_empty_lines.len());
-
-        let mut prev_is_empty_line = false;
-
-        for comment_or_empty in comments_or_empty_lines.into_iter().take(take_n) {
-            if let Some(comment) = comment_or_empty {
-                self.printer.word("// ");
-                self.printer.word(comment);
-                self.printer.hardbreak();
-                prev_is_empty_line = false;
-            } else if self.line_offset.is_some() {
-                // Do not print multiple consecutive empty lines
-                if !prev_is_empty_line {
-                    self.printer.hardbreak();
-                }
-
-                prev_is_empty_line = true;
-            }
+            trivia: SourceTrivia::collect(source, tokens),
+            min_blank_lines: 0,
         }
-
-        self.line_offset = Some(line_index);
     }
 
     pub fn format_syn_pat(&mut self, pat: &Pat) {
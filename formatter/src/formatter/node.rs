@@ -1,22 +1,60 @@
+use proc_macro2::LineColumn;
 use rstml::node::{Node, NodeBlock, NodeComment, NodeDoctype, NodeName, NodeText, RawText};
-use syn::spanned::Spanned;
+use syn::{spanned::Spanned, Block, Expr, ExprLit, Lit, Stmt};
 
-use crate::{formatter::Formatter, get_text_beween_spans};
+use crate::{
+    formatter::Formatter, get_text_beween_spans, ChildrenBraceStyle, TextStyle,
+    WhitespaceSensitivity,
+};
 
 impl Formatter<'_> {
     pub fn node(&mut self, node: &Node) {
-        self.flush_comments(node.span().start().line - 1, false);
-
-        match node {
-            Node::Element(ele) => self.element(ele),
-            Node::Fragment(frag) => self.fragment(frag),
-            Node::Text(text) => self.node_text(text),
-            Node::RawText(text) => self.raw_text(text, true),
-            Node::Comment(comment) => self.comment(comment),
-            Node::Doctype(doctype) => self.doctype(doctype),
-            Node::Block(block) => self.node_block(block),
-            Node::Custom(_) => todo!(),
-        };
+        self.child_node(node, None, None);
+    }
+
+    /// Prints a node of which the previous and next sibling are known, which decides whether its text can be converted
+    pub(crate) fn child_node(&mut self, node: &Node, previous: Option<&Node>, next: Option<&Node>) {
+        let span = node.span();
+        let skip = self.is_skipped(span.start());
+        self.leading_trivia(span.start(), false);
+
+        if skip {
+            self.skipped(node);
+        } else {
+            match node {
+                Node::Element(ele) => self.element(ele),
+                Node::Fragment(frag) => self.fragment(frag),
+                Node::Text(text) => match self.unquoted_text(text, previous, next) {
+                    Some(unquoted) => self.printer.word(unquoted),
+                    None => self.node_text(text),
+                },
+                Node::RawText(text) => {
+                    // Raw text is printed as written, including its comments
+                    match self.quoted_text(text) {
+                        Some(quoted) => self.printer.word(quoted),
+                        None => self.raw_text(text, true),
+                    }
+                    self.drop_trivia(span.start(), span.end());
+                }
+                Node::Comment(comment) => {
+                    self.hoist_comments(comment);
+                    self.comment(comment);
+                }
+                Node::Doctype(doctype) => {
+                    self.hoist_comments(doctype);
+                    self.doctype(doctype);
+                }
+                // Invalid blocks are printed as written, including their comments
+                Node::Block(block @ NodeBlock::Invalid(_)) => self.skipped(block),
+                Node::Block(NodeBlock::ValidBlock(block)) => {
+                    self.hoist_comments(block);
+                    self.child_block(block);
+                }
+                Node::Custom(custom) => self.skipped(custom),
+            };
+        }
+
+        self.trailing_comment(span.end());
     }
 
     pub fn comment(&mut self, comment: &NodeComment) {
@@ -32,7 +70,12 @@ impl Formatter<'_> {
     }
 
     pub fn node_text(&mut self, text: &NodeText) {
-        self.literal_str(&text.value);
+        if self.settings.reindent_quoted_text {
+            let literal = self.source_text(&text.value);
+            self.reindented_string(&literal);
+        } else {
+            self.literal_str(&text.value);
+        }
     }
 
     pub fn raw_text(&mut self, raw_text: &RawText, use_source_text: bool) {
@@ -41,14 +84,107 @@ impl Formatter<'_> {
             if let (Some(source), Some(span)) = (self.source, raw_text.join_spans()) {
                 get_text_beween_spans(source, span.start(), span.end()).to_string()
             } else {
-                raw_text.to_source_text(false)
-                .expect("Cannot format unquoted text, no source text available, or unquoted text is used outside of element.")
+                raw_text
+                    .to_source_text(false)
+                    .unwrap_or_else(|| raw_text.to_token_stream_string())
             }
         } else {
             raw_text.to_token_stream_string()
         };
 
-        self.string(&text, raw_text.span().start().column);
+        if use_source_text
+            && self.settings.wrap_text
+            && !text.contains("//")
+            && !text.contains("/*")
+        {
+            self.wrapped_text(&text);
+        } else if use_source_text && self.settings.reindent_unquoted_text {
+            self.reindented_string(&text);
+        } else {
+            self.string(&text, raw_text.span().start().column);
+        }
+    }
+
+    /// Fills the words of unquoted text onto as few lines as possible.
+    /// Consecutive whitespace collapses when unquoted text is rendered, so the original line breaks can be left out.
+    fn wrapped_text(&mut self, text: &str) {
+        self.printer.ibox(0);
+
+        let mut words = text.split_whitespace().peekable();
+        while let Some(word) = words.next() {
+            self.printer.word(word.to_owned());
+            if words.peek().is_some() {
+                self.printer.space();
+            }
+        }
+
+        self.printer.end();
+    }
+
+    /// The unquoted form of a text node when `text_style` is `Unquoted` and leaving out the quotes keeps the rendered text the same
+    fn unquoted_text(
+        &self,
+        text: &NodeText,
+        previous: Option<&Node>,
+        next: Option<&Node>,
+    ) -> Option<String> {
+        if self.settings.text_style != TextStyle::Unquoted {
+            return None;
+        }
+
+        // Unquoted text next to other text would be parsed as a single node
+        if previous.is_some_and(is_text) || next.is_some_and(is_text) {
+            return None;
+        }
+
+        let source = self.source?;
+        let span = text.value.span();
+        let value = text.value.value();
+        let literal = get_text_beween_spans(source, span.start(), span.end()).to_string();
+
+        (literal == format!("\"{value}\"")
+            && is_unquotable(&value)
+            && self.keeps_leading_whitespace(span.start()))
+        .then_some(value)
+    }
+
+    /// The quoted form of unquoted text when `text_style` is `Quoted` and the text can be quoted as is
+    fn quoted_text(&self, raw_text: &RawText) -> Option<String> {
+        if self.settings.text_style != TextStyle::Quoted {
+            return None;
+        }
+
+        let span = raw_text.join_spans()?;
+        let text = get_text_beween_spans(self.source?, span.start(), span.end()).to_string();
+
+        // Multi-line text and text with comments is kept as written
+        if text.contains('\n') || text.contains("//") || text.contains("/*") {
+            return None;
+        }
+
+        self.keeps_leading_whitespace(span.start()).then(|| {
+            let escaped = text.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("\"{escaped}\"")
+        })
+    }
+
+    /// Whether converting text that starts at `start` keeps the rendered whitespace in front of it.
+    /// Unquoted text renders the whitespace in front of it, which only matters when whitespace is significant.
+    fn keeps_leading_whitespace(&self, start: LineColumn) -> bool {
+        if self.settings.whitespace_sensitivity == WhitespaceSensitivity::Ignore {
+            return true;
+        }
+
+        let Some(source) = self.source else {
+            return false;
+        };
+
+        start.column > 0
+            && source
+                .line(start.line - 1)
+                .chars()
+                .nth(start.column - 1)
+                .is_some_and(|c| !c.is_whitespace())
     }
 
     pub fn node_name(&mut self, name: &NodeName) {
@@ -59,18 +195,68 @@ impl Formatter<'_> {
         }
     }
 
+    /// Prints a block child according to `children_brace_style` and `children_brace_spacing`
+    fn child_block(&mut self, block: &Block) {
+        let [Stmt::Expr(expr, None)] = &block.stmts[..] else {
+            return self.node_value_block_expr(block, false, false);
+        };
+
+        match expr {
+            Expr::Lit(ExprLit {
+                attrs,
+                lit: Lit::Str(lit_str),
+            }) if attrs.is_empty()
+                && self.settings.children_brace_style == ChildrenBraceStyle::WhenRequired =>
+            {
+                self.literal_str(lit_str)
+            }
+            expr if self.settings.children_brace_spacing => {
+                self.printer.word("{ ");
+                self.node_value_expr(expr, false, false, None);
+                self.printer.word(" }");
+            }
+            _ => self.node_value_block_expr(block, false, false),
+        }
+    }
+
     pub fn node_block(&mut self, block: &NodeBlock) {
         match block {
-            NodeBlock::Invalid { .. } => panic!("Invalid block will not pass cargo check"), // but we can keep them instead of panic
+            // Rust analyzer relies on invalid blocks (e.g. `{x.}`) for completions, keep them as is
+            NodeBlock::Invalid(_) => self.source_code(block),
             NodeBlock::ValidBlock(b) => self.node_value_block_expr(b, false, false),
         }
     }
 }
 
+fn is_text(node: &Node) -> bool {
+    matches!(node, Node::Text(_) | Node::RawText(_))
+}
+
+/// Whether text stays a single text node with the same content without quotes:
+/// it may not start or end with whitespace, and may only contain words and punctuation that do not need quotes
+/// (e.g. `'` starts a character literal and `<` or `{` start a new node)
+fn is_unquotable(text: &str) -> bool {
+    const PUNCTUATION: &str = ".,:;!?-+*=&|%^$@~#";
+
+    !text.is_empty()
+        && text.trim() == text
+        && text
+            .chars()
+            .all(|c| c == ' ' || c.is_ascii_alphanumeric() || PUNCTUATION.contains(c))
+        // Words starting with a digit may not be valid number literals (e.g. `1e`)
+        && text
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| word.starts_with(|c: char| c.is_ascii_digit()))
+            .all(|word| word.chars().all(|c| c.is_ascii_digit()))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::formatter::*;
-    use crate::test_helpers::{comment, doctype, format_with};
+    use crate::test_helpers::{
+        comment, doctype, format_element_from_string, format_recovered_element_from_string,
+        format_with,
+    };
 
     macro_rules! format_comment {
         ($($tt:tt)*) => {{
@@ -109,4 +295,131 @@ mod tests {
         let formatted = format_doctype!(< !DOCTYPE html   >);
         insta::assert_snapshot!(formatted, @"<!DOCTYPE html> ");
     }
+
+    #[test]
+    fn invalid_block() {
+        let formatted =
+            format_recovered_element_from_string(FormatterSettings::default(), "<div>{x.}</div>");
+        insta::assert_snapshot!(formatted, @"<div>{x.}</div>");
+    }
+
+    #[test]
+    fn invalid_braced_attribute_value() {
+        let formatted = format_recovered_element_from_string(
+            FormatterSettings::default(),
+            "<div   on:click={x.}   class=\"foo\" />",
+        );
+        insta::assert_snapshot!(formatted, @r#"<div on:click={x.} class="foo" />"#);
+    }
+
+    #[test]
+    fn text_style_quoted() {
+        let settings = FormatterSettings {
+            text_style: TextStyle::Quoted,
+            ..Default::default()
+        };
+
+        let formatted = format_element_from_string(
+            settings,
+            r#"<div><p>Hello, world!</p><p>Say "hi"</p><p>"Already quoted"</p><p>
+                    Text on
+                    several lines
+                </p></div>"#,
+        );
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <p>"Hello, world!"</p>
+            <p>"Say" "hi"</p>
+            <p>"Already quoted"</p>
+            <p>
+                Text on
+                several lines
+            </p>
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn text_style_unquoted() {
+        let settings = FormatterSettings {
+            text_style: TextStyle::Unquoted,
+            ..Default::default()
+        };
+
+        let formatted = format_element_from_string(
+            settings,
+            r#"<div><p>"Hello, world!"</p><p>"Don't"</p><p>"<tag>"</p><p>"1e5 items"</p><p>"first" "second"</p><p>"trailing "</p><b>"bold"</b>{x}"after block"</div>"#,
+        );
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <p>Hello, world!</p>
+            <p>"Don't"</p>
+            <p>"<tag>"</p>
+            <p>"1e5 items"</p>
+            <p>"first" "second"</p>
+            <p>"trailing "</p>
+            <b>bold</b>
+            {x}
+            after block
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn text_style_unquoted_strict_whitespace() {
+        let settings = FormatterSettings {
+            text_style: TextStyle::Unquoted,
+            whitespace_sensitivity: WhitespaceSensitivity::Strict,
+            ..Default::default()
+        };
+
+        let formatted = format_element_from_string(settings, r#"<p><b>"bold"</b> "spaced"</p>"#);
+        insta::assert_snapshot!(formatted, @r#"<p><b>bold</b> "spaced"</p>"#);
+    }
+
+    #[test]
+    fn children_brace_style_when_required() {
+        let settings = FormatterSettings {
+            children_brace_style: ChildrenBraceStyle::WhenRequired,
+            ..Default::default()
+        };
+
+        let formatted = format_element_from_string(
+            settings,
+            r#"<div><p>{"static text"}</p><p>{r"raw text"}</p><p>{ 42 }</p><p>{some_literal}</p></div>"#,
+        );
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <p>"static text"</p>
+            <p>r"raw text"</p>
+            <p>{42}</p>
+            <p>{some_literal}</p>
+        </div>
+        "#);
+    }
+
+    #[test]
+    fn children_brace_spacing() {
+        let settings = FormatterSettings {
+            children_brace_spacing: true,
+            ..Default::default()
+        };
+
+        let formatted = format_element_from_string(
+            settings,
+            r#"<div><p>{x}</p><p>{"text"}</p><p>{ let a = 1; a }</p></div>"#,
+        );
+        insta::assert_snapshot!(formatted, @r#"
+        <div>
+            <p>{ x }</p>
+            <p>{ "text" }</p>
+            <p>
+                {
+                    let a = 1;
+                    a
+                }
+            </p>
+        </div>
+        "#);
+    }
 }
@@ -0,0 +1,215 @@
+use proc_macro2::{LineColumn, Span};
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
+use crate::{collect_macros_in_tokens, formatter::Formatter, trivia::Trivia};
+
+impl Formatter<'_> {
+    /// Prints the comments and blank lines before the token that starts at `start`.
+    /// Blank lines before the first comment are left out when `trim_blank_lines` is set (e.g. at the start of a macro).
+    pub(crate) fn leading_trivia(&mut self, start: LineColumn, trim_blank_lines: bool) {
+        let mut trivia = self.trivia.take_leading(start);
+        if trim_blank_lines {
+            let first_comment = trivia
+                .iter()
+                .position(Trivia::is_comment)
+                .unwrap_or(trivia.len());
+            trivia.drain(..first_comment);
+        }
+
+        let mut min_blank_lines = std::mem::take(&mut self.min_blank_lines);
+        let mut blank_lines = 0;
+
+        for trivia in trivia {
+            match trivia {
+                Trivia::Comment { text, line_break } => {
+                    // Blank lines separate the previous sibling from the comments of the next one
+                    self.blank_lines(min_blank_lines.saturating_sub(blank_lines));
+                    min_blank_lines = 0;
+
+                    self.source_comment(&text, line_break);
+                    blank_lines = 0;
+                }
+                Trivia::BlankLine => {
+                    // Do not print more consecutive empty lines than allowed
+                    if blank_lines < self.settings.blank_lines_upper_bound {
+                        self.printer.hardbreak();
+                        blank_lines += 1;
+                    }
+                }
+            }
+        }
+
+        self.blank_lines(min_blank_lines.saturating_sub(blank_lines));
+    }
+
+    /// Prints the comments and blank lines after the last child of an element (or macro), before its end.
+    /// The caller is responsible for the line break after the trivia.
+    pub(crate) fn dangling_trivia(&mut self, mut trivia: Vec<Trivia>) {
+        // Blank lines after the last comment are left out
+        let last_comment = trivia
+            .iter()
+            .rposition(Trivia::is_comment)
+            .map_or(0, |idx| idx + 1);
+        trivia.truncate(last_comment);
+
+        let mut blank_lines = 0;
+        let mut trivia = trivia.into_iter().peekable();
+        while let Some(item) = trivia.next() {
+            let line_break = match item {
+                Trivia::Comment { text, line_break } => {
+                    self.comment_lines(&text);
+                    blank_lines = 0;
+                    line_break
+                }
+                Trivia::BlankLine => {
+                    if blank_lines >= self.settings.blank_lines_upper_bound {
+                        continue;
+                    }
+                    blank_lines += 1;
+                    true
+                }
+            };
+
+            match (trivia.peek().is_some(), line_break) {
+                (false, _) => {}
+                (true, true) => self.printer.hardbreak(),
+                (true, false) => self.printer.nbsp(),
+            }
+        }
+    }
+
+    /// Takes the comments and blank lines after the last child of an element or fragment,
+    /// and the comments within its closing tag, which are printed before the closing tag
+    pub(crate) fn closing_tag_trivia(&mut self, close_tag: Span) -> Vec<Trivia> {
+        let mut trivia = self.trivia.take_leading(close_tag.start());
+        trivia.extend(
+            self.trivia
+                .take_within(close_tag.start(), close_tag.end(), &[])
+                .into_iter()
+                .map(|text| Trivia::Comment {
+                    text,
+                    line_break: true,
+                }),
+        );
+
+        // Blank lines alone are not kept before the closing tag
+        if !trivia.iter().any(Trivia::is_comment) {
+            trivia.clear();
+        }
+        trivia
+    }
+
+    /// Whether the comment lines directly above the token that starts at `start` contain a `// leptosfmt::skip` comment
+    pub(crate) fn is_skipped(&self, start: LineColumn) -> bool {
+        self.trivia.is_skipped(start)
+    }
+
+    /// Prints a node or attribute exactly as written, including the comments within it
+    pub(crate) fn skipped<T: ToTokens>(&mut self, node: &T) {
+        self.source_code(node);
+        self.drop_trivia(node.span().start(), node.span().end());
+    }
+
+    /// Drops the comments within the given range, used when the source is printed verbatim
+    pub(crate) fn drop_trivia(&mut self, start: LineColumn, end: LineColumn) {
+        self.trivia.take_within(start, end, &[]);
+    }
+
+    /// Whether formatting `node` prints comments on their own lines before it,
+    /// either the comments above it or the ones within it that are hoisted
+    pub(crate) fn has_own_line_comments<T: ToTokens>(&self, node: &T) -> bool {
+        let span = node.span();
+        self.trivia
+            .leading(span.start())
+            .iter()
+            .any(Trivia::is_comment)
+            || self
+                .trivia
+                .has_comments_within(span.start(), span.end(), &self.nested_macros(node))
+    }
+
+    /// The ranges of the view macros nested within `node`
+    fn nested_macros<T: ToTokens>(&self, node: &T) -> Vec<(LineColumn, LineColumn)> {
+        collect_macros_in_tokens(node.to_token_stream(), &self.settings.macro_names)
+            .iter()
+            .map(|mac| (mac.span().start(), mac.delimiter.span().close().end()))
+            .collect()
+    }
+
+    /// Prints the comments within a node or attribute that cannot be kept in place
+    /// (e.g. between an attribute key and value, or within rust code) on their own lines, before it.
+    /// Comments within nested view macros are formatted along with the macro.
+    pub(crate) fn hoist_comments<T: ToTokens>(&mut self, node: &T) {
+        self.hoist_comments_between(node.span().start(), node.span().end(), node);
+    }
+
+    /// Same as `hoist_comments`, for the part of `node` from `start` to `end`
+    pub(crate) fn hoist_comments_between<T: ToTokens>(
+        &mut self,
+        start: LineColumn,
+        end: LineColumn,
+        node: &T,
+    ) {
+        let nested_macros = self.nested_macros(node);
+
+        for comment in self.trivia.take_within(start, end, &nested_macros) {
+            self.source_comment(&comment, true);
+        }
+    }
+
+    /// Prints the comment at the end of the line after the token that ends at `end`, if there is one
+    pub(crate) fn trailing_comment(&mut self, end: LineColumn) {
+        let Some(comment) = self.trivia.take_trailing(end) else {
+            return;
+        };
+
+        match comment.strip_prefix("//") {
+            Some(text) => self.printer.line_suffix(format!(" // {}", text.trim())),
+            None => self.printer.line_suffix(format!(" {comment}")),
+        }
+    }
+
+    /// Prints a `//` or `/* */` comment from the source at the current indentation,
+    /// followed by a line break (or a space for block comments that are followed by code on the same line)
+    pub(crate) fn source_comment(&mut self, comment: &str, line_break: bool) {
+        self.comment_lines(comment);
+
+        if line_break {
+            self.printer.hardbreak();
+        } else {
+            self.printer.nbsp();
+        }
+    }
+
+    pub(crate) fn comment_lines(&mut self, comment: &str) {
+        if let Some(text) = comment.strip_prefix("//") {
+            self.printer.word(format!("// {}", text.trim()));
+            return;
+        }
+
+        let mut lines = comment.lines().peekable();
+        while let Some(line) = lines.next() {
+            if !line.is_empty() {
+                self.printer.word(line.to_owned());
+            }
+            if lines.peek().is_some() {
+                self.printer.hardbreak();
+            }
+        }
+    }
+
+    /// Requests `blank_lines_lower_bound` blank lines before the next sibling, which is put on a line of its own
+    pub(crate) fn separate_siblings(&mut self) {
+        self.min_blank_lines = self
+            .settings
+            .blank_lines_lower_bound
+            .min(self.settings.blank_lines_upper_bound);
+    }
+
+    fn blank_lines(&mut self, count: usize) {
+        for _ in 0..count {
+            self.printer.hardbreak();
+        }
+    }
+}
@@ -3,20 +3,27 @@
 use std::path::Path;
 
 use crop::{Rope, RopeSlice};
+use leptosfmt_pretty_printer::{char_width, display_width};
 use proc_macro2::LineColumn;
-pub use source_file::{format_file_source, FormatError};
+pub use source_file::{
+    format_file_source, format_file_source_with_outcome, FormatError, FormatOutcome,
+};
 
 mod collect;
-mod collect_comments;
 mod formatter;
+mod parser;
 mod source_file;
+mod stats;
+mod trivia;
 mod view_macro;
 
 #[cfg(test)]
 mod test_helpers;
 
-pub use collect::collect_macros_in_file;
+pub use collect::{collect_macros_in_file, collect_macros_in_tokens};
 pub use formatter::*;
+pub use parser::{ParserPreset, ParserSettings};
+pub use stats::{view_macro_stats, ViewMacroStats};
 
 pub fn format_file(path: &Path, settings: FormatterSettings) -> Result<String, FormatError> {
     let file = std::fs::read_to_string(path)?;
@@ -27,8 +34,10 @@ fn get_text_beween_spans(rope: &Rope, start: LineColumn, end: LineColumn) -> Rop
     let start_byte: usize = line_column_to_byte(rope, start);
     let end_byte = line_column_to_byte(rope, end);
 
-    return rope.byte_slice(start_byte..end_byte);
+    rope.byte_slice(start_byte..end_byte)
 }
+
+/// Converts a `proc-macro2` position to a byte offset, its column counts chars (not bytes or display columns)
 fn line_column_to_byte(source: &Rope, point: proc_macro2::LineColumn) -> usize {
     let line_byte = source.byte_of_line(point.line - 1);
     let line = source.line(point.line - 1);
@@ -0,0 +1,313 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::{Mutex, PoisonError},
+};
+
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::ToTokens;
+use rstml::{node::Node, ParserConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::formatter::VOID_ELEMENTS;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum ParserPreset {
+    /// Default rstml parser configuration
+    #[default]
+    Rstml,
+    /// Parser configuration of the leptos 0.6 `view!` macro
+    Leptos06,
+    /// Parser configuration of the leptos 0.7 `view!` macro
+    Leptos07,
+    /// Parser configuration of the leptos 0.8 `view!` macro
+    Leptos08,
+}
+
+impl ParserPreset {
+    fn recover_block(self) -> bool {
+        !matches!(self, Self::Rstml)
+    }
+
+    fn raw_text_elements(self) -> &'static [&'static str] {
+        match self {
+            Self::Rstml | Self::Leptos06 => &[],
+            Self::Leptos07 | Self::Leptos08 => &["script", "style"],
+        }
+    }
+
+    fn always_self_closed_elements(self) -> &'static [&'static str] {
+        match self {
+            Self::Rstml => &[],
+            Self::Leptos06 | Self::Leptos07 | Self::Leptos08 => &VOID_ELEMENTS,
+        }
+    }
+
+    fn element_close_wildcard(self) -> bool {
+        matches!(self, Self::Leptos08)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ParserSettings {
+    /// Parser configuration to start from, the settings below override the preset
+    pub preset: ParserPreset,
+
+    /// Keep blocks that are not valid Rust (e.g. `{x.}`) instead of failing to parse the macro
+    pub recover_block: Option<bool>,
+
+    /// Elements whose children are parsed as raw text (e.g. `script` and `style`)
+    pub raw_text_elements: Option<Vec<String>>,
+
+    /// Elements that never have children, and therefore do not need a closing tag (e.g. `br` or `img`)
+    pub always_self_closed_elements: Option<Vec<String>>,
+
+    /// Allow elements to be closed with the `</_>` wildcard
+    pub element_close_wildcard: Option<bool>,
+}
+
+impl ParserSettings {
+    pub fn to_parser_config(&self) -> ParserConfig {
+        let raw_text_elements = match &self.raw_text_elements {
+            Some(elements) => static_names(elements),
+            None => self.preset.raw_text_elements().iter().copied().collect(),
+        };
+
+        let config = ParserConfig::new()
+            .recover_block(self.recover_block())
+            .raw_text_elements(raw_text_elements)
+            .always_self_closed_elements(self.always_self_closed_elements());
+
+        if self.element_close_wildcard() {
+            config.element_close_use_default_wildcard_ident(false)
+        } else {
+            config
+        }
+    }
+
+    fn recover_block(&self) -> bool {
+        self.recover_block
+            .unwrap_or_else(|| self.preset.recover_block())
+    }
+
+    fn always_self_closed_elements(&self) -> HashSet<&'static str> {
+        match &self.always_self_closed_elements {
+            Some(elements) => static_names(elements),
+            None => self
+                .preset
+                .always_self_closed_elements()
+                .iter()
+                .copied()
+                .collect(),
+        }
+    }
+
+    fn element_close_wildcard(&self) -> bool {
+        self.element_close_wildcard
+            .unwrap_or_else(|| self.preset.element_close_wildcard())
+    }
+
+    pub(crate) fn parse(&self, tokens: TokenStream) -> Option<Vec<Node>> {
+        let parser = rstml::Parser::new(self.to_parser_config());
+        if !self.recover_block() {
+            return parser.parse_simple(tokens).ok();
+        }
+
+        // Recovering from an invalid block may skip over tokens that could not be parsed,
+        // only accept the result when no tokens got lost, otherwise formatting would drop code.
+        let mut expected = Vec::new();
+        flatten_tokens(tokens.clone(), &mut expected);
+        let (nodes, _) = parser.parse_recoverable(tokens).split();
+
+        nodes
+            .filter(|nodes| {
+                let mut parsed = Vec::new();
+                flatten_tokens(
+                    nodes.iter().map(ToTokens::to_token_stream).collect(),
+                    &mut parsed,
+                );
+                parsed == expected
+            })
+            // The parser also recovers from missing and mismatched closing tags, which would get "fixed" when printed
+            .filter(|nodes| self.are_closed(nodes, &self.always_self_closed_elements()))
+    }
+
+    /// Whether every element and fragment is closed by a matching closing tag, or does not need one
+    fn are_closed(&self, nodes: &[Node], always_self_closed: &HashSet<&'static str>) -> bool {
+        nodes.iter().all(|node| match node {
+            Node::Element(element) => {
+                let open_tag = &element.open_tag;
+                let closed = match &element.close_tag {
+                    Some(close_tag) => {
+                        (close_tag.name == open_tag.name
+                            || self.element_close_wildcard() && close_tag.name.is_wildcard())
+                            && close_tag.generics == open_tag.generics
+                    }
+                    None => {
+                        open_tag.is_self_closed()
+                            || always_self_closed.contains(open_tag.name.to_string().as_str())
+                    }
+                };
+                closed && self.are_closed(&element.children, always_self_closed)
+            }
+            Node::Fragment(fragment) => {
+                fragment.tag_close.is_some()
+                    && self.are_closed(&fragment.children, always_self_closed)
+            }
+            _ => true,
+        })
+    }
+}
+
+/// Flattens a token stream into the text of its tokens, ignoring spacing between punctuation
+fn flatten_tokens(tokens: TokenStream, flattened: &mut Vec<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                flattened.push(open.to_string());
+                flatten_tokens(group.stream(), flattened);
+                flattened.push(close.to_string());
+            }
+            TokenTree::Punct(punct) => flattened.push(punct.as_char().to_string()),
+            token => flattened.push(token.to_string()),
+        }
+    }
+}
+
+/// rstml requires element names to be `'static`. Names of the presets are used as is, other names are interned,
+/// so that each distinct name is leaked at most once, no matter how often the settings are re-created
+/// (e.g. by an editor integration that reloads the configuration).
+fn static_names(names: &[String]) -> HashSet<&'static str> {
+    static INTERNED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+    names
+        .iter()
+        .map(|name| {
+            if let Some(name) = VOID_ELEMENTS
+                .iter()
+                .chain(&["script", "style"])
+                .find(|known| **known == name.as_str())
+            {
+                return *name;
+            }
+
+            let mut interned = INTERNED.lock().unwrap_or_else(PoisonError::into_inner);
+            match interned.get(name.as_str()) {
+                Some(name) => *name,
+                None => {
+                    let name: &'static str = Box::leak(name.clone().into_boxed_str());
+                    interned.insert(name);
+                    name
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use rstml::{
+        node::{Node, NodeElement},
+        Infallible,
+    };
+
+    use super::*;
+
+    fn parse(preset: ParserPreset, tokens: TokenStream) -> Option<Vec<Node>> {
+        ParserSettings {
+            preset,
+            ..Default::default()
+        }
+        .parse(tokens)
+    }
+
+    fn first_element(nodes: &[Node]) -> &NodeElement<Infallible> {
+        match &nodes[0] {
+            Node::Element(element) => element,
+            node => panic!("expected an element, got {node:?}"),
+        }
+    }
+
+    #[test]
+    fn leptos06_void_elements() {
+        let tokens = quote! { <div><br><input type="text"></div> };
+
+        assert!(parse(ParserPreset::Rstml, tokens.clone()).is_none());
+        let nodes = parse(ParserPreset::Leptos06, tokens).unwrap();
+        assert_eq!(first_element(&nodes).children.len(), 2);
+    }
+
+    #[test]
+    fn leptos07_raw_text_elements() {
+        let tokens = quote! { <style>.foo { color: red; }</style> };
+
+        let nodes = parse(ParserPreset::Leptos06, tokens.clone());
+        assert!(nodes.is_none_or(|nodes| {
+            !matches!(
+                first_element(&nodes).children.as_slice(),
+                [Node::RawText(_)]
+            )
+        }));
+
+        let nodes = parse(ParserPreset::Leptos07, tokens).unwrap();
+        assert!(matches!(
+            first_element(&nodes).children.as_slice(),
+            [Node::RawText(_)]
+        ));
+    }
+
+    #[test]
+    fn leptos08_element_close_wildcard() {
+        // Earlier presets only accept the wildcard by recovering from a mismatched closing tag
+        let diagnostics = |preset| {
+            let config = ParserSettings {
+                preset,
+                ..Default::default()
+            }
+            .to_parser_config();
+            let (nodes, diagnostics) = rstml::Parser::new(config)
+                .parse_recoverable(quote! { <{tag}>"hi"</_> })
+                .split();
+            assert!(nodes.is_some());
+            diagnostics.len()
+        };
+
+        assert_ne!(diagnostics(ParserPreset::Leptos07), 0);
+        assert_eq!(diagnostics(ParserPreset::Leptos08), 0);
+    }
+
+    #[test]
+    fn recovery_keeps_closing_tags() {
+        // Recovering from the invalid block must not recover from the tags around it
+        let unclosed = quote! { <div><span>{x.}</span> };
+        let mismatched = quote! { <div><span>{x.}</span></p> };
+        let wildcard = quote! { <div><span>{x.}</span></_> };
+
+        assert!(parse(ParserPreset::Leptos07, quote! { <div>{x.}</div> }).is_some());
+        assert!(parse(ParserPreset::Leptos07, unclosed).is_none());
+        assert!(parse(ParserPreset::Leptos07, mismatched).is_none());
+        assert!(parse(ParserPreset::Leptos07, wildcard.clone()).is_none());
+        assert!(parse(ParserPreset::Leptos08, wildcard).is_some());
+        assert!(parse(ParserPreset::Leptos07, quote! { <><br></p> }).is_none());
+    }
+
+    #[test]
+    fn static_names_are_leaked_once() {
+        let names = vec!["br".to_string(), "my-element".to_string()];
+        let first = static_names(&names);
+        let second = static_names(&names.clone());
+
+        assert!(first.contains("br") && first.contains("my-element"));
+        for name in &first {
+            let same = second.get(name).unwrap();
+            assert!(std::ptr::eq(*name, *same));
+        }
+    }
+}
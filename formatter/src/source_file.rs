@@ -1,15 +1,20 @@
 use std::{
     io::{self},
     ops::Range,
+    str::FromStr,
 };
 
 use crop::Rope;
 
+use proc_macro2::TokenStream;
 use syn::spanned::Spanned;
 use thiserror::Error;
 
 use crate::{
-    collect::collect_macros_in_file,
+    collect::{
+        collect_macros_in_file, collect_macros_in_tokens, has_skip_comment, has_skip_file_comment,
+        parent_indent,
+    },
     formatter::{format_macro, FormatterSettings},
     line_column_to_byte, ViewMacro,
 };
@@ -23,19 +28,106 @@ pub enum FormatError {
 }
 
 #[derive(Debug)]
-struct TextEdit {
-    range: Range<usize>,
-    new_text: String,
+pub(crate) struct TextEdit {
+    pub(crate) range: Range<usize>,
+    pub(crate) new_text: String,
+}
+
+impl TextEdit {
+    /// Whether applying the edit changes the source
+    pub(crate) fn changes(&self, source: &Rope) -> bool {
+        source.byte_slice(self.range.clone()) != self.new_text.as_str()
+    }
+}
+
+/// The result of formatting a file
+#[derive(Debug, PartialEq)]
+pub enum FormatOutcome {
+    /// The view macros of the file were formatted, the result may be the same as the source
+    Formatted(String),
+    /// The file was left untouched, because of a `// leptosfmt::skip-file` comment
+    /// or because it has no view macros to format
+    Skipped,
 }
 
 pub fn format_file_source(
     source: &str,
     settings: &FormatterSettings,
 ) -> Result<String, FormatError> {
-    let ast = syn::parse_file(source)?;
-    let rope = Rope::from(source);
-    let (mut rope, macros) = collect_macros_in_file(&ast, rope, &settings.macro_names);
-    format_source(&mut rope, macros, settings)
+    Ok(match format_file_source_with_outcome(source, settings)? {
+        FormatOutcome::Formatted(formatted) => formatted,
+        FormatOutcome::Skipped => source.to_string(),
+    })
+}
+
+/// Same as `format_file_source`, but tells whether the file was skipped
+pub fn format_file_source_with_outcome(
+    source: &str,
+    settings: &FormatterSettings,
+) -> Result<FormatOutcome, FormatError> {
+    with_view_macros(source, settings, |rope, macros| {
+        if macros.is_empty() {
+            return Ok(FormatOutcome::Skipped);
+        }
+        format_source(rope, macros, settings).map(FormatOutcome::Formatted)
+    })
+}
+
+/// Collects all view macros in `source` and hands them to `f`, together with the source as a rope.
+///
+/// Files that are not valid Rust (e.g. half-written code while editing) are scanned token by token instead,
+/// so that every view macro that parses on its own is still visited.
+pub(crate) fn with_view_macros<R>(
+    source: &str,
+    settings: &FormatterSettings,
+    f: impl FnOnce(&mut Rope, Vec<ViewMacro<'_>>) -> Result<R, FormatError>,
+) -> Result<R, FormatError> {
+    let mut rope = Rope::from(source);
+    if has_skip_file_comment(source) {
+        return f(&mut rope, Vec::new());
+    }
+
+    match syn::parse_file(source) {
+        Ok(ast) => {
+            let (mut rope, macros) = collect_macros_in_file(&ast, rope, settings);
+            f(&mut rope, macros)
+        }
+        Err(parse_error) => {
+            // Unbalanced delimiters can not be tokenized, in that case there is nothing we can do
+            let tokens = TokenStream::from_str(source).map_err(|_| parse_error)?;
+            let macros = collect_macros_in_tokens(tokens, &settings.macro_names);
+
+            let view_macros = macros
+                .iter()
+                .filter(|mac| !has_skip_comment(&rope, mac))
+                .filter_map(|mac| {
+                    let mut view_mac =
+                        ViewMacro::try_parse(parent_indent(&rope, mac), mac, &settings.parser)?;
+                    // Without a syntax tree, it is unknown whether the macro is in statement position
+                    view_mac.keep_delimiter = true;
+                    Some(view_mac)
+                })
+                .collect();
+
+            f(&mut rope, view_macros)
+        }
+    }
+}
+
+/// The edit that replaces a view macro with its formatted version
+pub(crate) fn macro_edit(
+    source: &Rope,
+    view_mac: &ViewMacro<'_>,
+    settings: &FormatterSettings,
+) -> TextEdit {
+    let mac = view_mac.inner();
+    let start_byte = line_column_to_byte(source, mac.path.span().start());
+    let end_byte = line_column_to_byte(source, mac.delimiter.span().close().end());
+
+    TextEdit {
+        range: start_byte..end_byte,
+        new_text: format_macro(view_mac, settings, Some(source)),
+    }
 }
 
 fn format_source(
@@ -43,21 +135,10 @@ fn format_source(
     macros: Vec<ViewMacro<'_>>,
     settings: &FormatterSettings,
 ) -> Result<String, FormatError> {
-    let mut edits = Vec::new();
-
-    for view_mac in macros {
-        let mac = view_mac.inner();
-        let start = mac.path.span().start();
-        let end = mac.delimiter.span().close().end();
-        let start_byte = line_column_to_byte(source, start);
-        let end_byte = line_column_to_byte(source, end);
-        let new_text = format_macro(&view_mac, settings, Some(source));
-
-        edits.push(TextEdit {
-            range: start_byte..end_byte,
-            new_text,
-        });
-    }
+    let edits: Vec<_> = macros
+        .iter()
+        .map(|view_mac| macro_edit(source, view_mac, settings))
+        .collect();
 
     let mut last_offset: isize = 0;
     for edit in edits {
@@ -77,9 +158,12 @@ fn format_source(
 
 #[cfg(test)]
 mod tests {
-    use indoc::indoc;
+    use indoc::{formatdoc, indoc};
 
-    use crate::{ExpressionFormatter, IndentationStyle};
+    use crate::{
+        AttributeWrap, ExpressionFormatter, IndentationStyle, MacroDelimiterStyle, ParserPreset,
+        ParserSettings,
+    };
 
     use super::*;
 
@@ -135,6 +219,595 @@ mod tests {
         "#);
     }
 
+    #[test]
+    fn invalid_rust_outside_view_macro() {
+        let source = indoc! {r#"
+            fn main() {
+                let x = ;
+                view! {  <div>  <span>"hello"</span></div>  };
+            }
+
+            fn other() {
+                leptos::view! {    <span>"world"</span>  }
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            let x = ;
+            view! {
+                <div>
+                    <span>"hello"</span>
+                </div>
+            };
+        }
+
+        fn other() {
+            leptos::view! { <span>"world"</span> }
+        }
+        "#);
+    }
+
+    #[test]
+    fn absolute_macro_path() {
+        let source = indoc! {r#"
+            fn main() {
+                ::leptos::view! {  <div>  <span>"hello"</span></div>  };
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            ::leptos::view! {
+                <div>
+                    <span>"hello"</span>
+                </div>
+            };
+        }
+        "#);
+
+        let invalid = source.replace("fn main() {", "fn main() { let x = ;");
+        let result = format_file_source(&invalid, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() { let x = ;
+            ::leptos::view! {
+                <div>
+                    <span>"hello"</span>
+                </div>
+            };
+        }
+        "#);
+    }
+
+    #[test]
+    fn skip_directives() {
+        let source = indoc! {r#"
+            fn main() {
+                // leptosfmt::skip
+                view! {  <div>  <span>"skipped"</span></div>  };
+
+                view! {
+                    <table>
+                        // leptosfmt::skip
+                        <tr>  <td>"a"</td>   <td>"b"</td>  </tr>
+                        <tr>  <td>"c"</td>   <td>"d"</td>  </tr>
+                    </table>
+                    <div
+                        // leptosfmt::skip
+                        style  =  "color: red"
+                        class  =  "foo"
+                    />
+                };
+            }
+
+            #[rustfmt::skip]
+            fn skipped() {
+                view! {  <div>  <span>"skipped"</span></div>  }
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            // leptosfmt::skip
+            view! {  <div>  <span>"skipped"</span></div>  };
+
+            view! {
+                <table>
+                    // leptosfmt::skip
+                    <tr>  <td>"a"</td>   <td>"b"</td>  </tr>
+                    <tr>
+                        <td>"c"</td>
+                        <td>"d"</td>
+                    </tr>
+                </table>
+                <div
+                    // leptosfmt::skip
+                    style  =  "color: red"
+                    class="foo"
+                />
+            };
+        }
+
+        #[rustfmt::skip]
+        fn skipped() {
+            view! {  <div>  <span>"skipped"</span></div>  }
+        }
+        "#);
+    }
+
+    #[test]
+    fn skip_directive_on_nested_macro() {
+        let source = indoc! {r#"
+            fn main() {
+                view! {
+                    <div>
+                        {move || {
+                            // leptosfmt::skip
+                            view! {  <span>"skipped"</span>  }
+                        }}
+                        {move || view! {  <span>"formatted"</span>  }}
+                    </div>
+                    <Show fallback=|| {
+                        // leptosfmt::skip
+                        view! {  <p>"skipped"</p>  }
+                    } />
+                };
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            view! {
+                <div>
+                    {move || {
+                        // leptosfmt::skip
+                        view! {  <span>"skipped"</span>  }
+                    }} {move || view! { <span>"formatted"</span> }}
+                </div>
+                <Show fallback=|| {
+                    // leptosfmt::skip
+                    view! {  <p>"skipped"</p>  }
+                } />
+            };
+        }
+        "#);
+    }
+
+    #[test]
+    fn skip_file_directive() {
+        let source = indoc! {r#"
+            // leptosfmt::skip-file
+            fn main() {
+                view! {  <div>  <span>"skipped"</span></div>  };
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        assert_eq!(result, source);
+    }
+
+    #[test]
+    fn skipped_outcome() {
+        let settings = Default::default();
+        let skipped = indoc! {r#"
+            // leptosfmt::skip-file
+            fn main() {
+                view! {  <span>"skipped"</span>  }
+            }
+        "#};
+        let without_macros = "fn main() {}";
+        let formatted = skipped.replace("// leptosfmt::skip-file\n", "");
+
+        assert_eq!(
+            format_file_source_with_outcome(skipped, &settings).unwrap(),
+            FormatOutcome::Skipped
+        );
+        assert_eq!(
+            format_file_source_with_outcome(without_macros, &settings).unwrap(),
+            FormatOutcome::Skipped
+        );
+        assert!(matches!(
+            format_file_source_with_outcome(&formatted, &settings).unwrap(),
+            FormatOutcome::Formatted(_)
+        ));
+    }
+
+    #[test]
+    fn skip_file_directive_only_at_top() {
+        let source = indoc! {r#"
+            fn main() {
+                // leptosfmt::skip-file
+                let directive = "// leptosfmt::skip-file";
+                view! {  <div>  <span>"formatted"</span></div>  };
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            // leptosfmt::skip-file
+            let directive = "// leptosfmt::skip-file";
+            view! {
+                <div>
+                    <span>"formatted"</span>
+                </div>
+            };
+        }
+        "#);
+    }
+
+    #[test]
+    fn skip_attributes() {
+        let source = indoc! {r#"
+            fn main() {
+                #[rustfmt::skip]
+                render(view! {  <div>  <span>"skipped"</span></div>  });
+
+                let closure = #[leptosfmt::skip] || view! {  <span>"skipped"</span>  };
+
+                match value {
+                    #[rustfmt::skip]
+                    Some(_) => view! {  <span>"skipped"</span>  },
+                    None => view! {  <span>"formatted"</span>  },
+                }
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            #[rustfmt::skip]
+            render(view! {  <div>  <span>"skipped"</span></div>  });
+
+            let closure = #[leptosfmt::skip] || view! {  <span>"skipped"</span>  };
+
+            match value {
+                #[rustfmt::skip]
+                Some(_) => view! {  <span>"skipped"</span>  },
+                None => view! { <span>"formatted"</span> },
+            }
+        }
+        "#);
+    }
+
+    #[test]
+    fn skip_attributes_until_end_of_item_in_invalid_rust() {
+        let source = indoc! {r#"
+            fn main() {
+                let x = ;
+                #[rustfmt::skip]
+                let x = Foo { a }.render(view! {  <span>"skipped"</span>  });
+                #[rustfmt::skip]
+                if a { b } else { view! {  <span>"skipped"</span>  } }
+                view! {  <span>"formatted"</span>  }
+            }
+
+            #[rustfmt::skip]
+            fn skipped() -> Foo<{ N }> where T: Bar<{ M }> {
+                view! {  <span>"skipped"</span>  }
+            }
+
+            fn formatted() {
+                view! {  <span>"formatted"</span>  }
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            let x = ;
+            #[rustfmt::skip]
+            let x = Foo { a }.render(view! {  <span>"skipped"</span>  });
+            #[rustfmt::skip]
+            if a { b } else { view! {  <span>"skipped"</span>  } }
+            view! { <span>"formatted"</span> }
+        }
+
+        #[rustfmt::skip]
+        fn skipped() -> Foo<{ N }> where T: Bar<{ M }> {
+            view! {  <span>"skipped"</span>  }
+        }
+
+        fn formatted() {
+            view! { <span>"formatted"</span> }
+        }
+        "#);
+    }
+
+    #[test]
+    fn other_macro_bodies_in_invalid_rust() {
+        let source = indoc! {r#"
+            macro_rules! rules {
+                () => { view! {  <span>"rules"</span>  } };
+            }
+
+            fn main() {
+                let x = ;
+                let tokens = quote! { view! {  <span>"quoted"</span>  } };
+                view! {  <span>"formatted"</span>  }
+            }
+        "#};
+
+        // The same as without the syntax error, where the bodies of other macros are not visited either
+        let valid = source.replace("let x = ;", "let x = 1;");
+        let expected = format_file_source(&valid, &Default::default()).unwrap();
+        let result = format_file_source(source, &Default::default()).unwrap();
+        assert_eq!(result.replace("let x = ;", "let x = 1;"), expected);
+        insta::assert_snapshot!(result, @r#"
+        macro_rules! rules {
+            () => { view! {  <span>"rules"</span>  } };
+        }
+
+        fn main() {
+            let x = ;
+            let tokens = quote! { view! {  <span>"quoted"</span>  } };
+            view! { <span>"formatted"</span> }
+        }
+        "#);
+    }
+
+    #[test]
+    fn skip_attribute_on_file() {
+        for attribute in ["#![rustfmt::skip]", "#![leptosfmt::skip]"] {
+            let source = formatdoc! {r#"
+                {attribute}
+
+                fn main() {{
+                    view! {{  <span>"skipped"</span>  }}
+                }}
+            "#};
+
+            assert!(syn::parse_file(&source).is_ok());
+            assert_eq!(format_file_source(&source, &Default::default()).unwrap(), source);
+        }
+    }
+
+    #[test]
+    fn skip_attributes_in_invalid_rust() {
+        let source = indoc! {r#"
+            #[rustfmt::skip]
+            fn skipped() {
+                let x = ;
+                view! {  <span>"skipped"</span>  }
+            }
+
+            mod inner {
+                #![leptosfmt::skip]
+                fn skipped() {
+                    view! {  <span>"skipped"</span>  }
+                }
+            }
+
+            fn main() {
+                #[rustfmt::skip]
+                let skipped = view! {  <span>"skipped"</span>  };
+                view! {  <span>"formatted"</span>  }
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        #[rustfmt::skip]
+        fn skipped() {
+            let x = ;
+            view! {  <span>"skipped"</span>  }
+        }
+
+        mod inner {
+            #![leptosfmt::skip]
+            fn skipped() {
+                view! {  <span>"skipped"</span>  }
+            }
+        }
+
+        fn main() {
+            #[rustfmt::skip]
+            let skipped = view! {  <span>"skipped"</span>  };
+            view! { <span>"formatted"</span> }
+        }
+        "#);
+    }
+
+    #[test]
+    fn blank_lines_upper_bound() {
+        let source = indoc! {r#"
+            fn main() {
+                view! {
+                    <header>"Header"</header>
+
+
+
+                    <main>
+                        <h1>"Title"</h1>
+
+
+                        <p>"Text"</p>
+                    </main>
+                }
+            }
+        "#};
+
+        let default_result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(default_result, @r#"
+        fn main() {
+            view! {
+                <header>"Header"</header>
+
+                <main>
+                    <h1>"Title"</h1>
+
+                    <p>"Text"</p>
+                </main>
+            }
+        }
+        "#);
+
+        let settings = FormatterSettings {
+            blank_lines_upper_bound: 2,
+            ..Default::default()
+        };
+        let result = format_file_source(source, &settings).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            view! {
+                <header>"Header"</header>
+
+
+                <main>
+                    <h1>"Title"</h1>
+
+
+                    <p>"Text"</p>
+                </main>
+            }
+        }
+        "#);
+    }
+
+    #[test]
+    fn blank_lines_lower_bound() {
+        let source = indoc! {r#"
+            fn main() {
+                view! {
+                    <header>"Header"</header>
+                    // main content
+                    <main>
+                        <h1>"Title"</h1>
+                        <p>"Text"</p>
+                    </main>
+                    <div
+                        class="footer"
+
+
+                        id="footer"
+                        role="contentinfo"
+                    >
+                        "Footer"
+                    </div>
+                }
+            }
+        "#};
+
+        let settings = FormatterSettings {
+            blank_lines_lower_bound: 1,
+            attr_wrap: AttributeWrap::Always,
+            ..Default::default()
+        };
+        let result = format_file_source(source, &settings).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            view! {
+                <header>"Header"</header>
+
+                // main content
+                <main>
+                    <h1>"Title"</h1>
+
+                    <p>"Text"</p>
+                </main>
+
+                <div
+                    class="footer"
+
+                    id="footer"
+                    role="contentinfo"
+                >
+                    "Footer"
+                </div>
+            }
+        }
+        "#);
+    }
+
+    #[test]
+    fn invalid_rust_unbalanced_delimiters() {
+        let source = indoc! {r#"
+            fn main() {
+                view! {  <div>  <span>"hello"</span></div>  };
+        "#};
+
+        let result = format_file_source(source, &Default::default());
+        assert!(matches!(result, Err(FormatError::ParseError(_))));
+    }
+
+    #[test]
+    fn leptos_parser_preset() {
+        let source = indoc! {r#"
+            fn main() {
+                view! {
+                    <style>
+                        .foo { color: red; }
+                    </style>
+                    <div>  <br> <input type="text">  </div>
+                    <{tag}>"hi"</_>
+                    <span>{x.}</span>
+                }
+            }
+        "#};
+
+        let settings = FormatterSettings {
+            parser: ParserSettings {
+                preset: ParserPreset::Leptos08,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let default_result = format_file_source(source, &Default::default()).unwrap();
+        assert_eq!(default_result, source);
+
+        let result = format_file_source(source, &settings).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            view! {
+                <style>
+                    .foo { color: red; }
+                </style>
+                <div>
+                    <br />
+                    <input type="text" />
+                </div>
+                <{tag}>"hi"</_>
+                <span>{x.}</span>
+            }
+        }
+        "#);
+    }
+
+    #[test]
+    fn parser_preset_overrides() {
+        let source = indoc! {r#"
+            fn main() {
+                view! { <div>  <my-br> <span>"hi"</span>  </div> };
+            }
+        "#};
+
+        let settings = FormatterSettings {
+            parser: ParserSettings {
+                preset: ParserPreset::Leptos08,
+                always_self_closed_elements: Some(vec!["my-br".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let result = format_file_source(source, &settings).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            view! {
+                <div>
+                    <my-br />
+                    <span>"hi"</span>
+                </div>
+            };
+        }
+        "#);
+    }
+
     #[test]
     fn fully_qualified_macro_path() {
         let source = indoc! {r#"
@@ -382,17 +1055,15 @@ mod tests {
         "#};
 
         let result = format_file_source(source, &Default::default()).unwrap();
-        insta::assert_snapshot!(result, @r###"
+        insta::assert_snapshot!(result, @r#"
         // comment outside view macro
         fn main() {
             view! {
                 // Top level comment
                 <div>
                     // This is one beautiful message
-                    // at the end of the line 1
-                    <span>"hello"</span>
-                    // at the end of the line 2
-                    <div>
+                    <span>"hello"</span> // at the end of the line 1
+                    <div> // at the end of the line 2
                         // double
                         // comments
                         <span>"hello"</span>
@@ -401,25 +1072,282 @@ mod tests {
                         // a function that returns the items we're iterating over; a signal is fine
                         each=move || { errors.clone().into_iter().enumerate() }
                         // a unique key for each item as a reference
-                        // yeah
-                        key=|(index, _error)| *index
+                        key=|(index, _error)| *index // yeah
                     />
-                    // same line comment
-                    <div>// with comment on the next line
+                    <div> // same line comment
+                        // with comment on the next line
                     </div>
                     // comments with url: https://example.com
                     <h1>"hi"</h1>
-                // comments with empty lines inbetween
+                    // comments with empty lines inbetween
 
-                // and some more
-                // on the next line
+                    // and some more
+                    // on the next line
                 </div>
                 // trailing comment
             };
         }
 
         // comment after view macro
-        "###);
+        "#);
+    }
+
+    #[test]
+    fn with_block_comments() {
+        let source = indoc! {r#"
+            fn main() {
+                view! {
+                    /* Top level comment */
+                    <div>
+                            /*
+                             * A comment that
+                             *   spans several lines
+                             */
+                    <span>"hello"</span>
+                    <input
+                        /* the type */
+                        type="text"
+                        /* a /* nested */ comment
+                           on two lines */
+                        value="a" />
+                    /* one */ /* line */
+                    </div>
+                };
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            view! {
+                /* Top level comment */
+                <div>
+                    /*
+                     * A comment that
+                     *   spans several lines
+                     */
+                    <span>"hello"</span>
+                    <input
+                        /* the type */
+                        type="text"
+                        /* a /* nested */ comment
+                           on two lines */
+                        value="a"
+                    />
+                    /* one */ /* line */
+                </div>
+            };
+        }
+        "#);
+    }
+
+    #[test]
+    fn with_comments_within_tags() {
+        let source = indoc! {r#"
+            fn main() {
+                view! {
+                    <div
+                        class= // the class
+                            "container"
+                        id="main"
+                        // before the bracket
+                    >
+                        <Show
+                            when=move || true
+                            fallback=|| view! {
+                                // nothing to show
+                                <p>"empty"</p>
+                            }
+                        />
+                    </div // closing
+                    >
+                    <span>"hello"</span>
+                };
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            view! {
+                <div
+                    // the class
+                    class="container"
+                    id="main"
+                    // before the bracket
+                >
+                    <Show
+                        when=move || true
+                        fallback=|| {
+                            view! {
+                                // nothing to show
+                                <p>"empty"</p>
+                            }
+                        }
+                    />
+                    // closing
+                </div>
+                <span>"hello"</span>
+            };
+        }
+        "#);
+    }
+
+    #[test]
+    fn hoisted_comments() {
+        let source = indoc! {r#"
+            fn main() {
+                view! { // scoped
+                    class=STYLE,
+                    <!DOCTYPE /* doctype */ html>
+                    < /* name */ div {..attrs /* spread */}>
+                        {value // value
+                        }
+                        <!-- "text" /* html comment */ -->
+                        <input on:input=move |_| {
+                            // handler
+                            set_value(1)
+                        } />
+                    </div>
+                };
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            // scoped
+            view! { class=STYLE,
+                /* doctype */
+                <!DOCTYPE html>
+                /* name */
+                <div
+                    /* spread */
+                    {..attrs}
+                >
+                    // value
+                    {value}
+                    /* html comment */
+                    <!-- "text" -->
+                    <input
+                        // handler
+                        on:input=move |_| { set_value(1) }
+                    />
+                </div>
+            };
+        }
+        "#);
+    }
+
+    #[test]
+    fn macro_not_at_line_start() {
+        let source = indoc! {r#"
+            fn main() {
+                let fits = Some(view! { <p class="container">"Some text that fits"</p> }).into_any();
+                let prefix = Some(view! { <p class="container">"Some longer text that does not fit on the line"</p> });
+                let suffix = Some(view! { <p class="container">"Some text that does not fit after all"</p> }).into_any();
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            let fits = Some(view! { <p class="container">"Some text that fits"</p> }).into_any();
+            let prefix = Some(view! {
+                <p class="container">"Some longer text that does not fit on the line"</p>
+            });
+            let suffix = Some(view! {
+                <p class="container">"Some text that does not fit after all"</p>
+            }).into_any();
+        }
+        "#);
+    }
+
+    #[test]
+    fn macro_suffix_with_comment_markers() {
+        let source = indoc! {r#"
+            fn main() {
+                let comment = link(view! { <p class="container">"Some text that fits"</p> }, url); // a long comment
+                let url = link(view! { <p class="container">"Some text"</p> }, "https://example.com/some/long/path");
+                let lifetime = view! { <p class="container">"Some text"</p> }.parse::<&'a str>('"', "//path/to/it"); // c
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            let comment = link(view! { <p class="container">"Some text that fits"</p> }, url); // a long comment
+            let url = link(view! {
+                <p class="container">"Some text"</p>
+            }, "https://example.com/some/long/path");
+            let lifetime = view! {
+                <p class="container">"Some text"</p>
+            }.parse::<&'a str>('"', "//path/to/it"); // c
+        }
+        "#);
+    }
+
+    #[test]
+    fn preserve_macro_delimiters() {
+        let source = indoc! {r#"
+            fn main() {
+                let parens = view!(<p>"parens"</p>);
+                let brackets = view![<p>"brackets"</p>];
+                let scoped = view!(cx, <p>"scoped"</p>);
+                let long = view!(<p class="container">"Some much longer text that does not fit on the line"</p>);
+            }
+        "#};
+
+        let result = format_file_source(source, &Default::default()).unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            let parens = view!(<p>"parens"</p>);
+            let brackets = view![<p>"brackets"</p>];
+            let scoped = view!(cx, <p>"scoped"</p>);
+            let long = view!(
+                <p class="container">"Some much longer text that does not fit on the line"</p>
+            );
+        }
+        "#);
+    }
+
+    #[test]
+    fn brace_macro_delimiters() {
+        let source = indoc! {r#"
+            fn main() {
+                let parens = view!(<p>"parens"</p>);
+                let brackets = view![<p>"brackets"</p>];
+                view!(<p>"method"</p>).into_any();
+                view!(<p>"try"</p>)?.into_any();
+                view!(<p>"index"</p>)[0].into_any();
+                view!(<p>"cast"</p>) as View;
+                view!(<p>"binary"</p>).len() + view!(<p>"right"</p>).len();
+                view! {
+                    <div>{view!(<p>"nested"</p>).into_any()} {view![<p>"expression"</p>]}</div>
+                }
+            }
+        "#};
+
+        let result = format_file_source(
+            source,
+            &FormatterSettings {
+                macro_delimiter: MacroDelimiterStyle::Brace,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        insta::assert_snapshot!(result, @r#"
+        fn main() {
+            let parens = view! { <p>"parens"</p> };
+            let brackets = view! { <p>"brackets"</p> };
+            view! { <p>"method"</p> }.into_any();
+            view! { <p>"try"</p> }?.into_any();
+            view!(<p>"index"</p>)[0].into_any();
+            view!(<p>"cast"</p>) as View;
+            view! { <p>"binary"</p> }.len() + view! { <p>"right"</p> }.len();
+            view! { <div>{view! { <p>"nested"</p> }.into_any()} {view! { <p>"expression"</p> }}</div> }
+        }
+        "#);
     }
 
     #[test]
@@ -478,15 +1406,15 @@ mod tests {
         "#};
 
         let result = format_file_source(source, &Default::default()).unwrap();
-        insta::assert_snapshot!(result, @r###"
+        insta::assert_snapshot!(result, @r#"
         fn main() {
             view! {
                 // parent div
                 <div>
 
                     // parent span
-                    // ok
                     <span>
+                        // ok
                         {
                             let a = 12;
 
@@ -499,7 +1427,7 @@ mod tests {
                 </div>
             };
         }
-        "###);
+        "#);
     }
 
     #[test]
@@ -0,0 +1,156 @@
+use rstml::node::Node;
+use serde::Serialize;
+use syn::spanned::Spanned;
+
+use crate::{
+    source_file::{macro_edit, with_view_macros},
+    FormatError, FormatterSettings,
+};
+
+/// Size metrics of a single view macro
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ViewMacroStats {
+    /// Line of the macro invocation (1-based)
+    pub line: usize,
+    /// Column of the macro invocation (1-based)
+    pub column: usize,
+    /// Number of lines the macro invocation spans
+    pub lines: usize,
+    /// Total number of nodes (elements, text, blocks, ...)
+    pub nodes: usize,
+    /// Deepest level of nesting, top level nodes have a depth of 1
+    pub depth: usize,
+    /// Total number of attributes over all elements
+    pub attributes: usize,
+    /// Whether the macro would change when formatted with the current settings
+    pub needs_formatting: bool,
+}
+
+/// Collects statistics for every view macro in the given source file.
+/// View macros nested in another view macro (e.g. `{move || view! { .. }}`) are not listed separately,
+/// they are formatted as part of the outer macro and count towards its `needs_formatting`.
+/// Their nodes and attributes are not counted.
+pub fn view_macro_stats(
+    source: &str,
+    settings: &FormatterSettings,
+) -> Result<Vec<ViewMacroStats>, FormatError> {
+    with_view_macros(source, settings, |rope, macros| {
+        Ok(macros
+            .iter()
+            .map(|view_mac| {
+                let mac = view_mac.inner();
+                let start = mac.path.span().start();
+                let end = mac.delimiter.span().close().end();
+                // The same edit `format_file_source` applies, so that the result agrees with `--check`
+                let edit = macro_edit(rope, view_mac, settings);
+
+                let mut stats = ViewMacroStats {
+                    line: start.line,
+                    column: start.column + 1,
+                    lines: end.line - start.line + 1,
+                    nodes: 0,
+                    depth: 0,
+                    attributes: 0,
+                    needs_formatting: edit.changes(rope),
+                };
+                stats.count_nodes(&view_mac.nodes, 1);
+                stats
+            })
+            .collect())
+    })
+}
+
+impl ViewMacroStats {
+    fn count_nodes(&mut self, nodes: &[Node], depth: usize) {
+        for node in nodes {
+            self.nodes += 1;
+            self.depth = self.depth.max(depth);
+
+            if let Node::Element(element) = node {
+                self.attributes += element.attributes().len();
+            }
+
+            if let Some(children) = node.children() {
+                self.count_nodes(children, depth + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn element_metrics() {
+        let source = indoc! {r#"
+            #[component]
+            fn test() -> impl IntoView {
+                view! {
+                    <div class="a" id="b">
+                        <span>"hello"</span>
+                        {move || view! { <p>{value}</p> }}
+                    </div>
+                    <Foo bar=1 />
+                }
+            }
+        "#};
+
+        let stats = view_macro_stats(source, &Default::default()).unwrap();
+        assert_eq!(
+            stats,
+            vec![ViewMacroStats {
+                line: 3,
+                column: 5,
+                lines: 7,
+                nodes: 5,
+                depth: 3,
+                attributes: 3,
+                needs_formatting: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn needs_formatting() {
+        let source = indoc! {r#"
+            fn test() {
+                view! { <div><span>"a"</span></div> };
+                view! { <div>"a"</div> };
+            }
+        "#};
+
+        let stats = view_macro_stats(source, &Default::default()).unwrap();
+        let needs_formatting: Vec<_> = stats.iter().map(|s| s.needs_formatting).collect();
+        assert_eq!(needs_formatting, vec![true, false]);
+    }
+
+    #[test]
+    fn needs_formatting_agrees_with_format_file_source() {
+        let source = indoc! {r#"
+            fn test() {
+                view! {
+                        <div>
+                            <span>"only the indentation is off"</span>
+                        </div>
+                };
+                let formatted = view! {
+                    <div>
+                        <span>"already formatted, after a prefix"</span>
+                    </div>
+                };
+            }
+        "#};
+
+        let stats = view_macro_stats(source, &Default::default()).unwrap();
+        let needs_formatting: Vec<_> = stats.iter().map(|s| s.needs_formatting).collect();
+        assert_eq!(needs_formatting, vec![true, false]);
+
+        let formatted = crate::format_file_source(source, &Default::default()).unwrap();
+        assert_ne!(formatted, source);
+        let stats = view_macro_stats(&formatted, &Default::default()).unwrap();
+        assert!(stats.iter().all(|s| !s.needs_formatting));
+    }
+}
@@ -68,7 +68,6 @@ pub(crate) use attribute;
 pub(crate) use comment;
 pub(crate) use doctype;
 pub(crate) use element;
-pub(crate) use element_from_string;
 pub(crate) use fragment;
 
 use crate::{Formatter, FormatterSettings};
@@ -124,8 +123,7 @@ pub fn format_with_source(
     let rope = Rope::from_str(source).unwrap();
     let mut printer = Printer::new(settings.to_printer_settings(Some(&rope)));
     let tokens = <proc_macro2::TokenStream as std::str::FromStr>::from_str(source).unwrap();
-    let whitespace = crate::collect_comments::extract_whitespace_and_comments(&rope, tokens);
-    let mut formatter = Formatter::with_source(&settings, &mut printer, &rope, whitespace);
+    let mut formatter = Formatter::with_source(&settings, &mut printer, &rope, tokens);
     run(&mut formatter);
     printer.eof()
 }
@@ -137,6 +135,18 @@ pub fn format_with(settings: FormatterSettings, run: impl FnOnce(&mut Formatter)
     printer.eof()
 }
 
+// Parses with block recovery enabled, such that invalid blocks (e.g. `{x.}`) end up in the node tree
+pub fn format_recovered_element_from_string(settings: FormatterSettings, source: &str) -> String {
+    let tokens = proc_macro2::TokenStream::from_str(source).unwrap();
+    let (nodes, _) = rstml::Parser::new(rstml::ParserConfig::new().recover_block(true))
+        .parse_recoverable(tokens)
+        .split();
+    let element = get_element(nodes.unwrap(), 0);
+    format_with_source(settings, source, |formatter| {
+        formatter.element(&element);
+    })
+}
+
 pub fn format_element_from_string(settings: FormatterSettings, source: &str) -> String {
     let element = element_from_string!(source);
     format_with_source(settings, source, |formatter| {
@@ -0,0 +1,417 @@
+use std::collections::HashMap;
+
+use crop::Rope;
+
+use proc_macro2::{LineColumn, Span, TokenStream};
+
+use crate::{
+    char_width,
+    collect::{line_comment, SKIP_COMMENT},
+    display_width, get_text_beween_spans,
+};
+
+/// A comment or blank line between two tokens
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Trivia {
+    /// A `//` or `/* */` comment including its delimiters, block comments are dedented to the column they start at.
+    /// `line_break` is false for block comments that are followed by code on the same line.
+    Comment {
+        text: String,
+        line_break: bool,
+    },
+    BlankLine,
+}
+
+impl Trivia {
+    pub(crate) fn is_comment(&self) -> bool {
+        matches!(self, Self::Comment { .. })
+    }
+}
+
+/// The comments and blank lines of a view macro, attached to the tokens around them.
+///
+/// Comments at the end of a line are trailing trivia of the token before them,
+/// all other comments and blank lines are leading trivia of the token after them.
+/// Nodes, attributes and closing tags print the trivia attached to their first and last token,
+/// trivia within them that cannot be kept in place is printed before them.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SourceTrivia {
+    /// Comments and blank lines before a token, keyed by the start of the token
+    leading: HashMap<LineColumn, Vec<Trivia>>,
+    /// Comments at the end of the line, keyed by the end of the token they follow
+    trailing: HashMap<LineColumn, String>,
+}
+
+impl SourceTrivia {
+    pub(crate) fn collect(source: &Rope, tokens: TokenStream) -> Self {
+        let mut trivia = Self::default();
+        let mut last_span: Option<Span> = None;
+
+        traverse_token_stream(tokens, &mut |span: Span| {
+            if let Some(last_span) = last_span {
+                trivia.attach(source, last_span.end(), span.start());
+            }
+            last_span = Some(span);
+        });
+
+        trivia
+    }
+
+    /// Attaches the trivia in the whitespace between the end of one token and the start of the next
+    fn attach(&mut self, source: &Rope, end: LineColumn, start: LineColumn) {
+        let text = get_text_beween_spans(source, end, start).to_string();
+        let line_start: String = source.line(end.line - 1).chars().take(end.column).collect();
+        let comments = comments_in_whitespace(&text, display_width(&line_start));
+        let last_line = start.line - end.line;
+
+        let mut comments = comments.into_iter().peekable();
+        let mut trailing = Vec::new();
+
+        // Comments that end the line of the previous token belong to that token
+        while let Some(comment) = comments.next_if(|c| last_line > 0 && c.end_line == 0) {
+            trailing.push(comment.text);
+        }
+
+        if !trailing.is_empty() {
+            self.trailing.insert(end, trailing.join(" "));
+        }
+
+        let mut leading = Vec::new();
+        let mut line = 1;
+        while let Some(comment) = comments.next() {
+            // Lines without a comment in between are blank lines
+            while line < comment.line {
+                leading.push(Trivia::BlankLine);
+                line += 1;
+            }
+
+            let next_line = comments.peek().map_or(last_line, |next| next.line);
+            leading.push(Trivia::Comment {
+                text: comment.text,
+                line_break: comment.end_line < next_line,
+            });
+            line = line.max(comment.end_line + 1);
+        }
+
+        while line < last_line {
+            leading.push(Trivia::BlankLine);
+            line += 1;
+        }
+
+        if !leading.is_empty() {
+            self.leading.insert(start, leading);
+        }
+    }
+
+    /// The trivia before the token that starts at `start`
+    pub(crate) fn leading(&self, start: LineColumn) -> &[Trivia] {
+        self.leading
+            .get(&start)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Whether the comment lines directly above the token that starts at `start` contain a `// leptosfmt::skip` comment
+    pub(crate) fn is_skipped(&self, start: LineColumn) -> bool {
+        self.leading(start)
+            .iter()
+            .rev()
+            .map_while(|trivia| match trivia {
+                Trivia::Comment { text, .. } => Some(text),
+                Trivia::BlankLine => None,
+            })
+            .any(|comment| line_comment(comment) == Some(SKIP_COMMENT))
+    }
+
+    pub(crate) fn take_leading(&mut self, start: LineColumn) -> Vec<Trivia> {
+        self.leading.remove(&start).unwrap_or_default()
+    }
+
+    pub(crate) fn take_trailing(&mut self, end: LineColumn) -> Option<String> {
+        self.trailing.remove(&end)
+    }
+
+    /// Whether `take_within` would return any comments
+    pub(crate) fn has_comments_within(
+        &self,
+        start: LineColumn,
+        end: LineColumn,
+        excluded: &[(LineColumn, LineColumn)],
+    ) -> bool {
+        self.leading.iter().any(|(pos, trivia)| {
+            start < *pos
+                && *pos <= end
+                && !is_excluded(pos, excluded)
+                && trivia.iter().any(Trivia::is_comment)
+        }) || self
+            .trailing
+            .keys()
+            .any(|pos| start <= *pos && *pos < end && !is_excluded(pos, excluded))
+    }
+
+    /// Removes the comments within the source from `start` to `end` and returns them in source order,
+    /// skipping the ones within the `excluded` ranges.
+    /// Trivia before the first token and after the last token are not within the range.
+    pub(crate) fn take_within(
+        &mut self,
+        start: LineColumn,
+        end: LineColumn,
+        excluded: &[(LineColumn, LineColumn)],
+    ) -> Vec<String> {
+        let mut leading: Vec<_> = self
+            .leading
+            .keys()
+            .filter(|pos| start < **pos && **pos <= end && !is_excluded(pos, excluded))
+            .copied()
+            .collect();
+        let mut trailing: Vec<_> = self
+            .trailing
+            .keys()
+            .filter(|pos| start <= **pos && **pos < end && !is_excluded(pos, excluded))
+            .copied()
+            .collect();
+
+        leading.sort();
+        trailing.sort();
+
+        let mut comments: Vec<_> = leading
+            .into_iter()
+            .flat_map(|pos| {
+                let comments = self.leading.remove(&pos).unwrap_or_default();
+                comments.into_iter().filter_map(move |trivia| match trivia {
+                    Trivia::Comment { text, .. } => Some((pos, text)),
+                    Trivia::BlankLine => None,
+                })
+            })
+            .chain(
+                trailing
+                    .into_iter()
+                    .filter_map(|pos| Some((pos, self.trailing.remove(&pos)?))),
+            )
+            .collect();
+
+        // A stable sort keeps the comments before the same token in order
+        comments.sort_by_key(|(pos, _)| *pos);
+        comments.into_iter().map(|(_, comment)| comment).collect()
+    }
+
+    /// Splits off the trivia of a nested view macro, which starts at `start` and ends at `end`
+    pub(crate) fn split_off(&mut self, start: LineColumn, end: LineColumn) -> SourceTrivia {
+        let leading_positions: Vec<_> = self
+            .leading
+            .keys()
+            .filter(|pos| (start..=end).contains(pos))
+            .copied()
+            .collect();
+        let trailing_positions: Vec<_> = self
+            .trailing
+            .keys()
+            .filter(|pos| (start..end).contains(pos))
+            .copied()
+            .collect();
+
+        SourceTrivia {
+            leading: leading_positions
+                .into_iter()
+                .map(|pos| (pos, self.leading.remove(&pos).unwrap()))
+                .collect(),
+            trailing: trailing_positions
+                .into_iter()
+                .map(|pos| (pos, self.trailing.remove(&pos).unwrap()))
+                .collect(),
+        }
+    }
+}
+
+struct Comment {
+    /// Line the comment starts on, relative to the start of the whitespace
+    line: usize,
+    /// Last line the comment spans, relative to the start of the whitespace
+    end_line: usize,
+    /// The comment including its delimiters, block comments are dedented to the column they start at
+    text: String,
+}
+
+/// Finds the `//` and `/* */` comments in the whitespace between two tokens.
+/// `start_column` is the display column of the first character of `text` in the source.
+fn is_excluded(pos: &LineColumn, excluded: &[(LineColumn, LineColumn)]) -> bool {
+    excluded
+        .iter()
+        .any(|(start, end)| (start..=end).contains(&pos))
+}
+
+fn comments_in_whitespace(text: &str, start_column: usize) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let (mut line, mut column) = (0, start_column);
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let len = if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if rest.starts_with("/*") {
+            block_comment_len(rest)
+        } else if c == '\n' {
+            line += 1;
+            column = 0;
+            rest = &rest[1..];
+            continue;
+        } else {
+            column += char_width(c);
+            rest = &rest[c.len_utf8()..];
+            continue;
+        };
+
+        let comment = &rest[..len];
+        let end_line = line + comment.matches('\n').count();
+        comments.push(Comment {
+            line,
+            end_line,
+            text: dedent_comment(comment, column),
+        });
+
+        column += display_width(comment.rsplit('\n').next().unwrap_or_default());
+        line = end_line;
+        rest = &rest[len..];
+    }
+
+    comments
+}
+
+/// Length of the (possibly nested) block comment at the start of `text`, or the rest of the text if it is not closed
+fn block_comment_len(text: &str) -> usize {
+    let mut depth = 0;
+    let mut idx = 0;
+
+    while idx < text.len() {
+        if text[idx..].starts_with("/*") {
+            depth += 1;
+            idx += 2;
+        } else if text[idx..].starts_with("*/") {
+            depth -= 1;
+            idx += 2;
+            if depth == 0 {
+                return idx;
+            }
+        } else {
+            idx += text[idx..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    text.len()
+}
+
+/// Removes the indentation of the line the comment starts on from the following lines,
+/// such that the comment keeps its shape when it is re-indented
+fn dedent_comment(comment: &str, column: usize) -> String {
+    comment
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            let line = if idx == 0 {
+                line
+            } else {
+                let indent = line
+                    .chars()
+                    .take(column)
+                    .take_while(|c| c.is_whitespace())
+                    .map(char::len_utf8)
+                    .sum();
+                &line[indent..]
+            };
+            line.trim_end()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn traverse_token_stream(tokens: TokenStream, cb: &mut impl FnMut(Span)) {
+    for token in tokens {
+        match token {
+            proc_macro2::TokenTree::Group(group) => {
+                cb(group.span_open());
+                traverse_token_stream(group.stream(), cb);
+                cb(group.span_close());
+            }
+            _ => cb(token.span()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collect(source: &str) -> SourceTrivia {
+        let rope = Rope::from(source);
+        let tokens = source.parse().unwrap();
+        SourceTrivia::collect(&rope, tokens)
+    }
+
+    fn pos(line: usize, column: usize) -> LineColumn {
+        LineColumn { line, column }
+    }
+
+    #[test]
+    fn attach_leading_and_trailing() {
+        let mut trivia = collect("a // after a\n\n// before b\nb /* inline */ c");
+
+        assert_eq!(
+            trivia.take_trailing(pos(1, 1)).as_deref(),
+            Some("// after a")
+        );
+        assert_eq!(
+            trivia.take_leading(pos(4, 0)),
+            vec![
+                Trivia::BlankLine,
+                Trivia::Comment {
+                    text: "// before b".to_owned(),
+                    line_break: true
+                }
+            ]
+        );
+        assert_eq!(
+            trivia.take_leading(pos(4, 15)),
+            vec![Trivia::Comment {
+                text: "/* inline */".to_owned(),
+                line_break: false
+            }]
+        );
+    }
+
+    #[test]
+    fn take_within_skips_excluded_ranges() {
+        let mut trivia = collect("a b // one\nc // two\nd");
+
+        let comments = trivia.take_within(pos(1, 0), pos(3, 1), &[(pos(2, 0), pos(2, 1))]);
+        assert_eq!(comments, vec!["// one".to_owned()]);
+        assert_eq!(trivia.take_trailing(pos(2, 1)).as_deref(), Some("// two"));
+    }
+
+    #[test]
+    fn block_comments_are_dedented_by_display_width() {
+        // The comment starts at display column 7, but at char column 5
+        let mut trivia = collect("\"日本\" /* a\n        * b */\nc");
+
+        assert_eq!(
+            trivia.take_leading(pos(3, 0)),
+            vec![Trivia::Comment {
+                text: "/* a\n * b */".to_owned(),
+                line_break: true
+            }]
+        );
+    }
+
+    #[test]
+    fn block_comments_are_dedented_by_tabs_as_one_column() {
+        // A tab before the comment counts as one column, the same as in the printer
+        let mut trivia = collect("a\t/* a\n   * b */\nc");
+
+        assert_eq!(
+            trivia.take_leading(pos(3, 0)),
+            vec![Trivia::Comment {
+                text: "/* a\n * b */".to_owned(),
+                line_break: true
+            }]
+        );
+    }
+}
@@ -1,29 +1,30 @@
-use std::collections::HashMap;
-
 use crop::Rope;
 use leptosfmt_prettyplease::MacroFormatter;
+use syn::spanned::Spanned;
 
-use crate::{Formatter, FormatterSettings, ViewMacro};
+use crate::{
+    collect::StatementMacros, trivia::SourceTrivia, Formatter, FormatterSettings, ViewMacro,
+};
 
 pub struct ViewMacroFormatter<'a> {
     settings: &'a FormatterSettings,
     source: Option<&'a Rope>,
-    line_offset: &'a mut Option<usize>,
-    comments: HashMap<usize, Option<String>>,
+    trivia: &'a mut SourceTrivia,
+    statement_macros: StatementMacros,
 }
 
 impl ViewMacroFormatter<'_> {
     pub fn new<'a>(
         settings: &'a FormatterSettings,
         source: Option<&'a Rope>,
-        line_offset: &'a mut Option<usize>,
-        comments: HashMap<usize, Option<String>>,
+        trivia: &'a mut SourceTrivia,
+        statement_macros: StatementMacros,
     ) -> ViewMacroFormatter<'a> {
         ViewMacroFormatter {
             settings,
             source,
-            line_offset,
-            comments,
+            trivia,
+            statement_macros,
         }
     }
 }
@@ -50,23 +51,43 @@ impl MacroFormatter for ViewMacroFormatter<'_> {
                 continue;
             }
 
-            let Some(m) = ViewMacro::try_parse(Default::default(), mac) else {
-                continue;
-            };
+            // A nested macro below a `// leptosfmt::skip` comment is kept as written
+            if self.trivia.is_skipped(mac.span().start()) {
+                let mut formatter = self.formatter(printer, mac);
+                formatter.leading_trivia(mac.span().start(), false);
+                formatter.skipped(mac);
+                return true;
+            }
 
-            let mut formatter = Formatter {
-                printer,
-                settings: self.settings,
-                source: self.source,
-                line_offset: *self.line_offset,
-                whitespace_and_comments: self.comments.clone(),
+            let Some(mut m) = ViewMacro::try_parse(Default::default(), mac, &self.settings.parser)
+            else {
+                continue;
             };
+            m.keep_delimiter = self.statement_macros.contains(mac);
 
-            formatter.view_macro(&m);
+            self.formatter(printer, mac).view_macro(&m);
             formatted = true;
-            *self.line_offset = formatter.line_offset;
         }
 
         formatted
     }
 }
+
+impl ViewMacroFormatter<'_> {
+    /// A formatter for the given nested macro, with the comments within it
+    fn formatter<'b>(
+        &'b mut self,
+        printer: &'b mut leptosfmt_pretty_printer::Printer,
+        mac: &syn::Macro,
+    ) -> Formatter<'b> {
+        Formatter {
+            printer,
+            settings: self.settings,
+            source: self.source,
+            trivia: self
+                .trivia
+                .split_off(mac.span().start(), mac.delimiter.span().close().end()),
+            min_blank_lines: 0,
+        }
+    }
+}
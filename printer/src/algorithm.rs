@@ -6,6 +6,7 @@ use std::borrow::Cow;
 use std::cmp;
 use std::collections::VecDeque;
 use std::iter;
+use unicode_width::UnicodeWidthChar;
 
 #[derive(Clone, Copy, PartialEq)]
 pub enum Breaks {
@@ -22,6 +23,10 @@ pub struct BreakToken {
     pub no_break: Option<char>,
     pub if_nonempty: bool,
     pub never_break: bool,
+    // Always breaks, and takes the place of the next break (see `Printer::line_suffix`)
+    pub line_suffix: bool,
+    // The next line is not indented, e.g. within text that is printed as written
+    pub no_indent: bool,
 }
 
 #[derive(Clone, Copy)]
@@ -82,6 +87,8 @@ pub struct Printer {
     indent: usize,
     // Buffered indentation to avoid writing trailing whitespace
     pending_indentation: usize,
+    // Whether the line was ended by a line suffix, and nothing has been printed since
+    after_line_suffix: bool,
 }
 
 #[derive(Clone)]
@@ -103,6 +110,7 @@ impl Printer {
             print_stack: Vec::new(),
             indent: 0,
             pending_indentation: 0,
+            after_line_suffix: false,
         }
     }
 
@@ -178,7 +186,7 @@ impl Printer {
         if self.scan_stack.is_empty() {
             self.print_string(string);
         } else {
-            let len = string.len() as isize;
+            let len = display_width(&string) as isize;
             self.buf.push(BufEntry {
                 token: Token::String(string),
                 size: len,
@@ -188,6 +196,27 @@ impl Printer {
         }
     }
 
+    /// Takes `width` columns of the first line for text before the printed code (e.g. `let x = ` before a macro)
+    pub fn reserve_start(&mut self, width: usize) {
+        self.space = cmp::max(self.space - width as isize, self.settings.min_space);
+    }
+
+    /// Takes `width` columns of the current line for text after the printed code (e.g. `.into_any()` after a macro),
+    /// which is taken into account when deciding whether the enclosing boxes fit, but is not printed
+    pub fn reserve_end(&mut self, width: usize) {
+        let width = width as isize;
+        if self.scan_stack.is_empty() {
+            self.space -= width;
+        } else {
+            self.buf.push(BufEntry {
+                token: Token::String(Cow::Borrowed("")),
+                size: width,
+            });
+            self.right_total += width;
+            self.check_stream();
+        }
+    }
+
     pub fn offset(&mut self, offset: isize) {
         match &mut self.buf.last_mut().token {
             Token::Break(token) => token.offset += offset,
@@ -327,18 +356,42 @@ impl Printer {
     }
 
     fn print_break(&mut self, token: BreakToken, size: isize) {
-        let fits = token.never_break
-            || match self.get_top() {
-                PrintFrame::Fits(..) => true,
-                PrintFrame::Broken(.., Breaks::Consistent) => false,
-                PrintFrame::Broken(.., Breaks::Inconsistent) => size <= self.space,
-            };
+        if self.after_line_suffix && token.never_break {
+            // Spaces at the start of the line after a line suffix are meaningless,
+            // the next break still only determines the indentation of the line
+            if let Some(no_break) = token.no_break {
+                self.after_line_suffix = false;
+                self.print_indent();
+                self.out.push(no_break);
+                self.space -= char_width(no_break) as isize;
+            }
+            return;
+        }
+
+        if self.after_line_suffix {
+            // The line suffix already ended the line, this break only determines the indentation of the next line
+            self.after_line_suffix = false;
+            if !matches!(self.get_top(), PrintFrame::Fits(..)) {
+                let indent = self.break_indent(token);
+                self.pending_indentation = usize::try_from(indent).unwrap_or(0);
+                self.space = cmp::max(self.settings.margin - indent, self.settings.min_space);
+            }
+            return;
+        }
+
+        let fits = !token.line_suffix
+            && (token.never_break
+                || match self.get_top() {
+                    PrintFrame::Fits(..) => true,
+                    PrintFrame::Broken(.., Breaks::Consistent) => false,
+                    PrintFrame::Broken(.., Breaks::Inconsistent) => size <= self.space,
+                });
         if fits {
             self.pending_indentation += token.blank_space;
             self.space -= token.blank_space as isize;
             if let Some(no_break) = token.no_break {
                 self.out.push(no_break);
-                self.space -= no_break.len_utf8() as isize;
+                self.space -= char_width(no_break) as isize;
             }
         } else {
             if let Some(pre_break) = token.pre_break {
@@ -349,21 +402,39 @@ impl Printer {
                 self.out.push('\r');
             }
             self.out.push('\n');
-            let indent = self.indent as isize + token.offset;
+            let indent = self.break_indent(token);
             self.pending_indentation = usize::try_from(indent).unwrap_or(0);
             self.space = cmp::max(self.settings.margin - indent, self.settings.min_space);
             if !token.post_break.is_empty() {
                 self.print_indent();
                 self.out.push_str(token.post_break);
-                self.space -= token.post_break.len() as isize;
+                self.space -= display_width(token.post_break) as isize;
             }
+            self.after_line_suffix = token.line_suffix;
+        }
+    }
+
+    /// Indentation of the line that starts after the given break
+    fn break_indent(&self, token: BreakToken) -> isize {
+        if token.no_indent {
+            0
+        } else {
+            self.indent as isize + token.offset
         }
     }
 
     fn print_string(&mut self, string: Cow<'static, str>) {
+        if self.after_line_suffix {
+            // Whitespace at the start of the line after a line suffix is meaningless
+            if string.trim().is_empty() {
+                return;
+            }
+            self.after_line_suffix = false;
+        }
+
         self.print_indent();
         self.out.push_str(&string);
-        self.space -= string.len() as isize;
+        self.space -= display_width(&string) as isize;
     }
 
     fn print_indent(&mut self) {
@@ -384,3 +455,95 @@ impl Printer {
         self.pending_indentation = 0;
     }
 }
+
+/// Width of text in terminal columns, East Asian wide characters (e.g. CJK) and emoji take up two columns
+pub fn display_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Width of a char in terminal columns, tabs and other control characters count as one column
+pub fn char_width(c: char) -> usize {
+    c.width().unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn printer() -> Printer {
+        Printer::new(PrinterSettings {
+            margin: 40,
+            tab_spaces: 4,
+            min_space: 20,
+            crlf_line_endings: false,
+            hard_tabs: false,
+        })
+    }
+
+    fn never_break(blank_space: usize) -> BreakToken {
+        BreakToken {
+            blank_space,
+            never_break: true,
+            ..BreakToken::default()
+        }
+    }
+
+    #[test]
+    fn line_suffix_takes_place_of_next_break() {
+        let mut p = printer();
+        p.cbox_indent();
+        p.word("a");
+        p.line_suffix(" // comment");
+        p.hardbreak();
+        p.word("b");
+        p.end();
+
+        assert_eq!(p.eof(), "a // comment\n    b");
+    }
+
+    #[test]
+    fn line_suffix_followed_by_never_break() {
+        let mut p = printer();
+        p.cbox_indent();
+        p.word("a");
+        p.line_suffix(" // comment");
+        p.scan_break(never_break(1));
+        p.hardbreak();
+        p.word("b");
+        p.scan_break(never_break(1));
+        p.word("c");
+        p.end();
+
+        assert_eq!(p.eof(), "a // comment\n    b c");
+    }
+
+    #[test]
+    fn hardbreak_without_indent() {
+        let mut p = printer();
+        p.cbox_indent();
+        p.word("<pre>");
+        p.word(" a");
+        p.hardbreak_without_indent();
+        p.word("  b");
+        p.hardbreak();
+        p.word("</pre>");
+        p.end();
+
+        assert_eq!(p.eof(), "<pre> a\n  b\n    </pre>");
+    }
+
+    #[test]
+    fn tabs_count_as_one_column() {
+        assert_eq!(display_width("a\tb"), 3);
+        assert_eq!(display_width("日本"), 4);
+
+        let mut p = printer();
+        p.cbox(0);
+        p.word("\t".repeat(20));
+        p.space();
+        p.word("a".repeat(20));
+        p.end();
+
+        assert_eq!(p.eof(), format!("{}\n{}", "\t".repeat(20), "a".repeat(20)));
+    }
+}
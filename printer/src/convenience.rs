@@ -69,6 +69,26 @@ impl Printer {
         self.spaces(algorithm::SIZE_INFINITY as usize);
     }
 
+    /// A forced line break after which the line is not indented, for text that has to be printed as written
+    pub fn hardbreak_without_indent(&mut self) {
+        self.scan_break(BreakToken {
+            blank_space: algorithm::SIZE_INFINITY as usize,
+            no_indent: true,
+            ..BreakToken::default()
+        });
+    }
+
+    /// Prints text that has to be at the end of a line (e.g. a `//` comment), followed by a forced line break.
+    /// The line break takes the place of the next break, so no empty line is printed when a break follows.
+    pub fn line_suffix<S: Into<Cow<'static, str>>>(&mut self, suffix: S) {
+        self.word(suffix);
+        self.scan_break(BreakToken {
+            blank_space: algorithm::SIZE_INFINITY as usize,
+            line_suffix: true,
+            ..BreakToken::default()
+        });
+    }
+
     pub fn space_if_nonempty(&mut self) {
         self.scan_break(BreakToken {
             blank_space: 1,